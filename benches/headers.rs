@@ -0,0 +1,133 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use httplus::headers::{name_eq, Header};
+use httplus::Request;
+
+const NAMES: [&str; 8] = [
+    "Host",
+    "User-Agent",
+    "Accept",
+    "Accept-Encoding",
+    "Connection",
+    "Content-Type",
+    "Content-Length",
+    "Transfer-Encoding",
+];
+
+fn header_names(c: &mut Criterion) {
+    // what the parser used to do for every header: allocate a lowercase copy to compare
+    c.bench_function("name compare to_lowercase", |b| {
+        b.iter(|| {
+            NAMES
+                .iter()
+                .filter(|n| black_box(n).to_lowercase() == "content-length")
+                .count()
+        })
+    });
+    c.bench_function("name compare name_eq", |b| {
+        b.iter(|| {
+            NAMES
+                .iter()
+                .filter(|n| name_eq(black_box(n), "content-length"))
+                .count()
+        })
+    });
+}
+
+fn header_new(c: &mut Criterion) {
+    c.bench_function("header new", |b| {
+        b.iter(|| {
+            NAMES
+                .iter()
+                .map(|n| Header::new(format!("{}: value", black_box(n)).into_bytes()).unwrap())
+                .collect::<Vec<Header>>()
+        })
+    });
+    #[cfg(feature = "unchecked")]
+    c.bench_function("header new_unchecked", |b| {
+        b.iter(|| {
+            NAMES
+                .iter()
+                .map(|n| Header::new_unchecked(black_box(n).to_string(), "value".to_owned()))
+                .collect::<Vec<Header>>()
+        })
+    });
+}
+
+fn parse(c: &mut Criterion) {
+    let mut wire = b"POST /upload HTTP/1.1\r\n".to_vec();
+    for name in NAMES.iter().filter(|n| **n != "Transfer-Encoding") {
+        let value = if *name == "Content-Length" {
+            "4"
+        } else {
+            "value"
+        };
+        wire.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+    wire.extend_from_slice(b"\r\nBODY");
+
+    c.bench_function("parse request headers", |b| {
+        b.iter(|| {
+            let mut r = Request::default();
+            r.update_raw(&mut black_box(wire.clone())).unwrap();
+            r
+        })
+    });
+}
+
+// the head arriving a byte at a time should cost about the same as arriving at once
+fn fragmented(c: &mut Criterion) {
+    let mut wire = b"GET / HTTP/1.1\r\n".to_vec();
+    for i in 0..100 {
+        wire.extend_from_slice(format!("X-Header-{}: {}\r\n", i, i).as_bytes());
+    }
+    wire.extend_from_slice(b"\r\n");
+
+    c.bench_function("parse 100 headers at once", |b| {
+        b.iter(|| {
+            let mut r = Request::default();
+            r.update_raw(&mut black_box(wire.clone())).unwrap();
+            r
+        })
+    });
+    c.bench_function("parse 100 headers a byte at a time", |b| {
+        b.iter(|| {
+            let mut r = Request::default();
+            for byte in black_box(&wire) {
+                r.update_raw(&mut vec![*byte]).unwrap();
+            }
+            r
+        })
+    });
+}
+
+fn with_capacity(c: &mut Criterion) {
+    const BODY: usize = 1024 * 1024;
+    let mut wire =
+        format!("POST /upload HTTP/1.1\r\nContent-Length: {}\r\n\r\n", BODY).into_bytes();
+    let head = wire.len();
+    wire.resize(head + BODY, b'x');
+
+    // the body arrives the way it would from a socket, a piece at a time
+    let feed = |mut r: Request| {
+        for piece in wire.chunks(16 * 1024) {
+            r.update_raw(&mut piece.to_vec()).unwrap();
+        }
+        r
+    };
+    c.bench_function("parse 1MB body default", |b| {
+        b.iter(|| feed(black_box(Request::default())))
+    });
+    c.bench_function("parse 1MB body with_capacity", |b| {
+        b.iter(|| feed(black_box(Request::with_capacity(1, head + BODY))))
+    });
+}
+
+criterion_group!(
+    benches,
+    header_names,
+    header_new,
+    parse,
+    fragmented,
+    with_capacity
+);
+criterion_main!(benches);
@@ -0,0 +1,8 @@
+#![no_main]
+
+use httplus::headers::Header;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Header::new(data.to_vec());
+});
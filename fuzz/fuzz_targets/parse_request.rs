@@ -0,0 +1,24 @@
+#![no_main]
+
+use httplus::Request;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // the first byte picks how big the pieces handed to update_raw are, so that
+    // requests split at every possible point get exercised
+    let Some((step, data)) = data.split_first() else {
+        return;
+    };
+    let step = (*step as usize).max(1);
+
+    let mut r = Request::default();
+    for piece in data.chunks(step) {
+        if r.update_raw(&mut piece.to_vec()).is_err() {
+            return;
+        }
+    }
+    if r.body_complete() {
+        let mut replayed = Request::default();
+        let _ = replayed.update_raw(&mut r.dump());
+    }
+});
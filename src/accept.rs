@@ -0,0 +1,297 @@
+use crate::errors::Errors;
+use crate::headers::{is_tchar, split_outside_quotes, unquote};
+use crate::util;
+use std::cmp::{Ordering, Reverse};
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc7231#section-5.3.1
+
+    weight = OWS ";" OWS "q=" qvalue
+    qvalue = ( "0" [ "." 0*3DIGIT ] )
+           / ( "1" [ "." 0*3("0") ] )
+*/
+
+// a quality value that can be used as a map key
+#[derive(Debug, Clone, Copy)]
+pub struct OrderedF32(pub f32);
+
+impl PartialEq for OrderedF32 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrderedF32 {}
+
+impl PartialOrd for OrderedF32 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedF32 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+// split a list like "gzip;q=0.8, br" into its (lowercase) tokens and their weights,
+// a missing weight is 1 and an element with an unparsable weight is skipped
+pub fn parse_weighted(value: &str) -> Vec<(String, f32)> {
+    let mut weighted = vec![];
+    for element in value.split(',') {
+        let mut parts = element.split(';').map(|p| p.trim());
+        let token = match parts.next() {
+            Some(token) if !token.is_empty() => token.to_ascii_lowercase(),
+            _ => continue,
+        };
+
+        let mut quality = Some(1.0);
+        for param in parts {
+            if let Some((name, value)) = param.split_once('=') {
+                if name.trim().eq_ignore_ascii_case("q") {
+                    quality = util::qvalue(value.trim()).ok();
+                }
+            }
+        }
+        if let Some(quality) = quality {
+            weighted.push((token, quality));
+        }
+    }
+    weighted
+}
+
+// https://datatracker.ietf.org/doc/html/rfc7231#section-5.3.2
+// (quoted with line comments since the grammar holds a "*/*")
+//
+//     Accept = #( media-range [ accept-params ] )
+//
+//     media-range    = ( "*/*"
+//                      / ( type "/" "*" )
+//                      / ( type "/" subtype )
+//                      ) *( OWS ";" OWS parameter )
+//     accept-params  = weight *( accept-ext )
+//     accept-ext = OWS ";" OWS token [ "=" ( token / quoted-string ) ]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRange {
+    // lowercased, "*" for a wildcard
+    pub media_type: String,
+    pub subtype: String,
+    // the parameters before the weight, names lowercased and values unquoted
+    pub params: Vec<(String, String)>,
+    pub quality: f32,
+}
+
+impl MediaRange {
+    // a media type such as a Content-Type value parses the same way, its quality is 1
+    pub fn parse(value: &str) -> Result<MediaRange, Errors> {
+        let invalid = || Errors::MediaRange(value.to_owned());
+        let ows = |c| c == ' ' || c == '\t';
+        let is_token = |s: &str| !s.is_empty() && s.bytes().all(is_tchar);
+
+        let mut parts = split_outside_quotes(value, ';').into_iter();
+        let (media_type, subtype) = parts
+            .next()
+            .unwrap_or_default()
+            .trim_matches(ows)
+            .split_once('/')
+            .ok_or_else(invalid)?;
+        if !is_token(media_type) || !is_token(subtype) || (media_type == "*" && subtype != "*") {
+            return Err(invalid());
+        }
+
+        let mut range = MediaRange {
+            media_type: media_type.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            params: vec![],
+            quality: 1.0,
+        };
+        for param in parts {
+            let (name, value) = param.split_once('=').ok_or_else(invalid)?;
+            let name = name.trim_matches(ows);
+            let value = value.trim_matches(ows);
+            if name.eq_ignore_ascii_case("q") {
+                // anything after the weight is an accept-ext, which is ignored
+                range.quality = util::qvalue(value)?;
+                break;
+            }
+            if !is_token(name) {
+                return Err(invalid());
+            }
+            range
+                .params
+                .push((name.to_ascii_lowercase(), unquote(value)));
+        }
+        Ok(range)
+    }
+
+    /*
+        Media ranges can be overridden by more specific media ranges or
+        specific media types.  If more than one media range applies to a
+        given type, the most specific reference has precedence.
+    */
+    pub fn specificity(&self) -> u8 {
+        let specificity = match (self.media_type.as_str(), self.subtype.as_str()) {
+            ("*", _) => 0,
+            (_, "*") => 1,
+            _ => 2,
+        };
+        specificity + u8::from(!self.params.is_empty())
+    }
+
+    pub fn matches(&self, media_type: &MediaRange) -> bool {
+        (self.media_type == "*" || self.media_type == media_type.media_type)
+            && (self.subtype == "*" || self.subtype == media_type.subtype)
+            && self.params.iter().all(|p| media_type.params.contains(p))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Accept {
+    // highest quality first, more specific ranges first among equal qualities
+    pub ranges: Vec<MediaRange>,
+}
+
+impl Accept {
+    pub fn parse(value: &str) -> Result<Accept, Errors> {
+        let mut ranges = split_outside_quotes(value, ',')
+            .into_iter()
+            .filter(|element| !element.trim_matches(|c| c == ' ' || c == '\t').is_empty())
+            .map(MediaRange::parse)
+            .collect::<Result<Vec<MediaRange>, Errors>>()?;
+        ranges.sort_by_key(|r| Reverse((OrderedF32(r.quality), r.specificity())));
+        Ok(Accept { ranges })
+    }
+
+    // the quality the most specific matching range gives media_type, 0 when it is not acceptable
+    // NOTE: without any ranges every media type is acceptable
+    pub fn quality(&self, media_type: &str) -> f32 {
+        if self.ranges.is_empty() {
+            return 1.0;
+        }
+        let media_type = match MediaRange::parse(media_type) {
+            Ok(media_type) => media_type,
+            Err(_) => return 0.0,
+        };
+        self.ranges
+            .iter()
+            .filter(|r| r.matches(&media_type))
+            .max_by_key(|r| r.specificity())
+            .map_or(0.0, |r| r.quality)
+    }
+
+    // the available media type with the highest quality, the first one listed wins a tie
+    pub fn best_match<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        let mut best = None;
+        for media_type in available {
+            let quality = self.quality(media_type);
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((*media_type, quality));
+            }
+        }
+        best.map(|(media_type, _)| media_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_parse() {
+        let accept = Accept::parse("text/html, application/json;q=0.9, */*;q=0.8, text/*").unwrap();
+        assert_eq!(
+            accept
+                .ranges
+                .iter()
+                .map(|r| format!("{}/{};{}", r.media_type, r.subtype, r.quality))
+                .collect::<Vec<String>>(),
+            vec!["text/html;1", "text/*;1", "application/json;0.9", "*/*;0.8"]
+        );
+
+        let accept = Accept::parse("Text/HTML;Level=\"1\";q=0.5;ext=x,,").unwrap();
+        assert_eq!(
+            accept.ranges,
+            vec![MediaRange {
+                media_type: "text".to_owned(),
+                subtype: "html".to_owned(),
+                params: vec![("level".to_owned(), "1".to_owned())],
+                quality: 0.5,
+            }]
+        );
+
+        for value in ["text", "*/html", "text/", "text/html;level", "a b/c"] {
+            assert_eq!(
+                Accept::parse(value),
+                Err(Errors::MediaRange(value.to_owned()))
+            );
+        }
+        assert_eq!(
+            Accept::parse("text/html;q=2"),
+            Err(Errors::QualityValue("2".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_accept_quality() {
+        // the example from https://datatracker.ietf.org/doc/html/rfc7231#section-5.3.2
+        let accept = Accept::parse(
+            "text/*;q=0.3, text/html;q=0.7, text/html;level=1, text/html;level=2;q=0.4, */*;q=0.5",
+        )
+        .unwrap();
+        let cases = [
+            ("text/html;level=1", 1.0),
+            ("text/html", 0.7),
+            ("text/plain", 0.3),
+            ("image/jpeg", 0.5),
+            ("text/html;level=2", 0.4),
+            ("text/html;level=3", 0.7),
+        ];
+        for (media_type, quality) in cases {
+            assert_eq!(accept.quality(media_type), quality, "{}", media_type);
+        }
+    }
+
+    #[test]
+    fn test_best_match() {
+        let accept = Accept::parse("text/html, application/json;q=0.9, */*;q=0.8").unwrap();
+        assert_eq!(
+            accept.best_match(&["application/json", "text/html"]),
+            Some("text/html")
+        );
+        assert_eq!(
+            accept.best_match(&["image/png", "application/json"]),
+            Some("application/json")
+        );
+        assert_eq!(
+            accept.best_match(&["image/png", "image/gif"]),
+            Some("image/png")
+        );
+
+        let accept = Accept::parse("application/json, text/*;q=0").unwrap();
+        assert_eq!(accept.best_match(&["text/html"]), None);
+        assert_eq!(
+            Accept::default().best_match(&["text/html", "application/json"]),
+            Some("text/html")
+        );
+    }
+
+    #[test]
+    fn test_parse_weighted() {
+        assert_eq!(
+            parse_weighted("gzip;q=0.8, BR , deflate ; q=0.5,, identity;q=0, bad;q=2"),
+            vec![
+                ("gzip".to_owned(), 0.8),
+                ("br".to_owned(), 1.0),
+                ("deflate".to_owned(), 0.5),
+                ("identity".to_owned(), 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ordered_f32() {
+        assert!(OrderedF32(0.5) < OrderedF32(1.0));
+        assert_eq!(OrderedF32(0.5), OrderedF32(0.5));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::errors::Errors;
+use crate::headers::{Header, Headers};
+use crate::util;
+
+/*
+    https://www.rfc-editor.org/rfc/rfc7230#section-4.1
+
+    chunked-body   = *chunk
+                     last-chunk
+                     trailer-part
+                     CRLF
+
+    chunk          = chunk-size [ chunk-ext ] CRLF
+                     chunk-data CRLF
+    chunk-size     = 1*HEXDIG
+    last-chunk     = 1*("0") [ chunk-ext ] CRLF
+
+    chunk-data     = 1*OCTET ; a sequence of chunk-size octets
+
+    trailer-part   = *( header-field CRLF )
+*/
+
+// size lines and trailer fields are buffered until their CRLF arrives, so cap them
+const MAX_LINE_LENGTH: usize = 8192;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum State {
+    #[default]
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailer,
+    Done,
+}
+
+/*
+    chunk-ext      = *( ";" chunk-ext-name [ "=" chunk-ext-val ] )
+    chunk-ext-name = token
+    chunk-ext-val  = token / quoted-string
+*/
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkExt {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl ChunkExt {
+    // parse everything after the first ";" of a chunk size line
+    pub fn parse_all(extensions: &[u8]) -> Result<Vec<ChunkExt>, Errors> {
+        let extensions = String::from_utf8(extensions.to_vec())?;
+
+        let mut parsed = vec![];
+        if extensions.trim().is_empty() {
+            return Ok(parsed);
+        }
+        let mut chars = extensions.chars().peekable();
+        loop {
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|c| !matches!(c, ';' | '=' | ' ' | '\t')) {
+                name.push(c);
+            }
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            if name.is_empty() {
+                return Err(Errors::Chunk("chunk extension name must not be empty"));
+            }
+
+            let mut value = None;
+            if chars.next_if_eq(&'=').is_some() {
+                while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+                let mut v = String::new();
+                if chars.next_if_eq(&'"').is_some() {
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(c) => v.push(c),
+                                None => return Err(Errors::Chunk("unterminated quoted-string")),
+                            },
+                            Some(c) => v.push(c),
+                            None => return Err(Errors::Chunk("unterminated quoted-string")),
+                        }
+                    }
+                } else {
+                    while let Some(c) = chars.next_if(|c| !matches!(c, ';' | ' ' | '\t')) {
+                        v.push(c);
+                    }
+                }
+                value = Some(v);
+                while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            }
+            parsed.push(ChunkExt { name, value });
+
+            match chars.next() {
+                Some(';') => continue,
+                None => break,
+                Some(_) => return Err(Errors::Chunk("invalid chunk extension")),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+// a push decoder: wire bytes go in via feed, the decoded payload and trailers come out
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkDecoder {
+    state: State,
+    line: Vec<u8>,
+    consumed: usize,
+    decoded: usize,
+    pub body: Vec<u8>,
+    pub trailers: Headers,
+    // frame the body without keeping it, see ParseOptions::headers_only
+    pub discard_body: bool,
+    #[cfg(feature = "extensions")]
+    pub extensions: Vec<Vec<ChunkExt>>,
+}
+
+impl ChunkDecoder {
+    pub fn is_complete(&self) -> bool {
+        self.state == State::Done
+    }
+
+    // number of wire bytes consumed so far, including framing
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    // number of payload bytes decoded so far, also counted when the body is discarded
+    pub fn decoded(&self) -> usize {
+        self.decoded
+    }
+
+    // decode as much of data as possible and return how many bytes were used
+    // once the final CRLF has been seen any remaining bytes are left alone
+    pub fn feed(&mut self, data: &[u8]) -> Result<usize, Errors> {
+        let mut at = 0;
+        while at < data.len() {
+            match self.state {
+                State::Size => {
+                    if !self.read_line(data, &mut at)? {
+                        break;
+                    }
+                    let line = std::mem::take(&mut self.line);
+                    self.state = match self.parse_size_line(&line)? {
+                        0 => State::Trailer,
+                        size => State::Data(size),
+                    };
+                }
+                State::Data(remaining) => {
+                    let available = remaining.min(data.len() - at);
+                    if !self.discard_body {
+                        self.body.extend_from_slice(&data[at..at + available]);
+                    }
+                    at += available;
+                    self.decoded += available;
+                    self.state = if available == remaining {
+                        State::DataEnd
+                    } else {
+                        State::Data(remaining - available)
+                    };
+                }
+                State::DataEnd => {
+                    self.line.push(data[at]);
+                    at += 1;
+                    match self.line.as_slice() {
+                        b"\r" => {}
+                        b"\r\n" => {
+                            self.line.clear();
+                            self.state = State::Size;
+                        }
+                        _ => return Err(Errors::Chunk("chunk data must be followed by CRLF")),
+                    }
+                }
+                State::Trailer => {
+                    if !self.read_line(data, &mut at)? {
+                        break;
+                    }
+                    let line = std::mem::take(&mut self.line);
+                    if line.is_empty() {
+                        self.state = State::Done;
+                    } else {
+                        self.trailers.values.push(Header::new(line)?);
+                    }
+                }
+                State::Done => break,
+            }
+        }
+        self.consumed += at;
+        Ok(at)
+    }
+
+    // buffer bytes up to the next LF, returning true once a whole line (minus CRLF) is in self.line
+    fn read_line(&mut self, data: &[u8], at: &mut usize) -> Result<bool, Errors> {
+        let lf = util::scan_for_lf(data, *at);
+        for byte in data[*at..lf.unwrap_or(data.len())].iter() {
+            if self.line.last() == Some(&b'\r') {
+                return Err(Errors::BareCarriageReturn);
+            }
+            self.line.push(*byte);
+            if self.line.len() > MAX_LINE_LENGTH {
+                return Err(Errors::Chunk("chunk line is too long"));
+            }
+        }
+        match lf {
+            Some(lf) => {
+                *at = lf + 1;
+                if self.line.last() != Some(&b'\r') {
+                    return Err(Errors::BareLineFeed);
+                }
+                self.line.pop();
+                Ok(true)
+            }
+            None => {
+                *at = data.len();
+                Ok(false)
+            }
+        }
+    }
+
+    fn parse_size_line(&mut self, line: &[u8]) -> Result<usize, Errors> {
+        let (size, extensions) = match line.iter().position(|b| *b == b';') {
+            Some(i) => (&line[..i], &line[i + 1..]),
+            None => (line, &[][..]),
+        };
+
+        // whitespace is tolerated between the size and its extensions
+        let mut size = size;
+        while let Some((last, rest)) = size.split_last() {
+            if *last != b' ' && *last != b'\t' {
+                break;
+            }
+            size = rest;
+        }
+        if size.is_empty() || !size.iter().all(u8::is_ascii_hexdigit) {
+            return Err(Errors::Chunk("invalid chunk size"));
+        }
+
+        // all hex digits, so this is valid utf8
+        let size = String::from_utf8_lossy(size);
+        let size = match usize::from_str_radix(&size, 16) {
+            Ok(size) => size,
+            Err(_) => return Err(Errors::Chunk("chunk size is too large")),
+        };
+
+        // extensions are always checked, the extensions feature only decides if they are kept
+        let extensions = ChunkExt::parse_all(extensions)?;
+        #[cfg(feature = "extensions")]
+        self.extensions.push(extensions);
+        #[cfg(not(feature = "extensions"))]
+        let _ = extensions;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"4\r\nWiki\r\n7\r\npedia i\r\nB\r\nn \r\nchunks.\r\n0\r\n\r\n";
+
+    #[test]
+    fn test_decode() {
+        let mut d = ChunkDecoder::default();
+        assert_eq!(d.feed(BODY), Ok(BODY.len()));
+        assert!(d.is_complete());
+        assert_eq!(d.body, b"Wikipedia in \r\nchunks.".to_vec());
+        assert_eq!(d.consumed(), BODY.len());
+    }
+
+    #[test]
+    fn test_decode_byte_at_a_time() {
+        let mut d = ChunkDecoder::default();
+        for byte in BODY.iter() {
+            assert!(!d.is_complete());
+            assert_eq!(d.feed(&[*byte]), Ok(1));
+        }
+        assert!(d.is_complete());
+        assert_eq!(d.body, b"Wikipedia in \r\nchunks.".to_vec());
+    }
+
+    #[test]
+    fn test_trailers_and_leftover() {
+        let mut d = ChunkDecoder::default();
+        let data = b"3\r\nabc\r\n0\r\nExpires: never\r\nX-Sum: 1\r\n\r\nGET / HTTP/1.1\r\n";
+        assert_eq!(d.feed(data), Ok(data.len() - 16));
+        assert!(d.is_complete());
+        assert_eq!(d.body, b"abc".to_vec());
+        assert_eq!(d.trailers.len(), 2);
+        assert_eq!(d.trailers.values[0].to_string(), "Expires: never");
+        assert_eq!(d.feed(b"more"), Ok(0));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut d = ChunkDecoder::default();
+        assert_eq!(d.feed(b"x\r\n"), Err(Errors::Chunk("invalid chunk size")));
+
+        let mut d = ChunkDecoder::default();
+        assert_eq!(
+            d.feed(b"3\r\nabcd\r\n"),
+            Err(Errors::Chunk("chunk data must be followed by CRLF"))
+        );
+
+        let mut d = ChunkDecoder::default();
+        assert_eq!(
+            d.feed(b"fffffffffffffffffffff\r\n"),
+            Err(Errors::Chunk("chunk size is too large"))
+        );
+
+        let mut d = ChunkDecoder::default();
+        assert_eq!(d.feed(b"3\nabc"), Err(Errors::BareLineFeed));
+    }
+
+    #[test]
+    fn test_extensions_are_skipped() {
+        let mut d = ChunkDecoder::default();
+        let data = b"3 ; name=value\r\nabc\r\n0;last\r\n\r\n";
+        assert_eq!(d.feed(data), Ok(data.len()));
+        assert_eq!(d.body, b"abc".to_vec());
+
+        let mut d = ChunkDecoder::default();
+        assert_eq!(
+            d.feed(b"3;=1\r\n"),
+            Err(Errors::Chunk("chunk extension name must not be empty"))
+        );
+        let mut d = ChunkDecoder::default();
+        assert_eq!(
+            d.feed(b"3;a=\"b\r\n"),
+            Err(Errors::Chunk("unterminated quoted-string"))
+        );
+    }
+
+    #[cfg(feature = "extensions")]
+    #[test]
+    fn test_extensions() {
+        let mut d = ChunkDecoder::default();
+        let data = b"3;a=1;b=\"x;\\\"y\"\r\nabc\r\n2\r\nde\r\n0;last\r\n\r\n";
+        assert_eq!(d.feed(data), Ok(data.len()));
+        assert_eq!(
+            d.extensions,
+            vec![
+                vec![
+                    ChunkExt {
+                        name: "a".to_owned(),
+                        value: Some("1".to_owned())
+                    },
+                    ChunkExt {
+                        name: "b".to_owned(),
+                        value: Some("x;\"y".to_owned())
+                    },
+                ],
+                vec![],
+                vec![ChunkExt {
+                    name: "last".to_owned(),
+                    value: None
+                }],
+            ]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use crate::errors::Errors;
+use crate::framer::Framer;
+use crate::{ParseOptions, Request};
+
+/*
+    https://www.rfc-editor.org/rfc/rfc7230#section-6.6
+
+    A server that receives a "close" connection option MUST initiate a
+    close of the connection (see below) after it sends the final response
+    to the request that contained "close".  The server SHOULD send a
+    "close" connection option in its final response on that connection.
+    The server MUST NOT process any further requests received on that
+    connection.
+*/
+
+// the requests read from one client connection, in order, until one of them closes it
+// a server loop is then: read from the socket, push, respond to every request returned
+#[derive(Debug, Clone, Default)]
+pub struct Connection {
+    framer: Framer,
+    closed: bool,
+}
+
+impl Connection {
+    pub fn new(options: ParseOptions) -> Self {
+        Connection {
+            framer: Framer::new(options),
+            closed: false,
+        }
+    }
+
+    // every request completed by data, once a request does not keep the connection alive
+    // (see Request::keep_alive) it is the last one returned and anything after it is ignored
+    // an upgrade request is returned as soon as its head is complete and is the last one too,
+    // see Framer::push for where the bytes of the new protocol are
+    // NOTE: after an error the connection can't be used any more, just like a Request
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<Request>, Errors> {
+        if self.closed {
+            return Ok(vec![]);
+        }
+        self.framer.push(data)?;
+
+        let mut complete = vec![];
+        while let Some(request) = self.framer.next_complete() {
+            let keep_alive = request.keep_alive();
+            complete.push(request);
+            if !keep_alive {
+                self.closed = true;
+                break;
+            }
+        }
+        Ok(complete)
+    }
+
+    // the request that is still being received
+    pub fn pending(&self) -> &Request {
+        self.framer.pending()
+    }
+
+    // true once a request has asked for the connection to be closed after its response
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    // true once an upgrade request has been returned, the connection no longer carries HTTP
+    pub fn is_upgraded(&self) -> bool {
+        self.framer.is_upgraded()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(requests: &[Request]) -> Vec<&str> {
+        requests.iter().map(|r| r.request_line.as_str()).collect()
+    }
+
+    #[test]
+    fn test_pipelined_fragments() {
+        let wire = b"GET /a HTTP/1.1\r\nHost: x\r\n\r\nPOST /b HTTP/1.1\r\nContent-Length: 5\r\n\r\nhelloPUT /c HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1\r\nz\r\n0\r\n\r\n";
+        // the second split is inside of "Content-Length"
+        let second = wire.windows(9).position(|w| w == b"Content-L").unwrap() + 9;
+        let fragments = [&wire[..10], &wire[10..second], &wire[second..], &b""[..]];
+
+        let mut connection = Connection::default();
+        let mut received = vec![];
+        for fragment in fragments {
+            let requests = connection.push(fragment).unwrap();
+            received.push(lines(&requests).len());
+            if let Some(b) = requests.iter().find(|r| r.request_line.starts_with("POST")) {
+                assert_eq!(b.body(), b"hello".to_vec());
+            }
+        }
+        assert_eq!(received, vec![0, 1, 2, 0]);
+        assert!(connection.pending().raw.is_empty());
+        assert!(!connection.is_closed());
+    }
+
+    #[test]
+    fn test_close() {
+        let mut connection = Connection::default();
+        let requests = connection
+            .push(b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\nConnection: close\r\n\r\nGET /c HTTP/1.1\r\n\r\n")
+            .unwrap();
+        assert_eq!(lines(&requests), vec!["GET /a HTTP/1.1", "GET /b HTTP/1.1"]);
+        assert!(connection.is_closed());
+        assert!(connection
+            .push(b"GET /d HTTP/1.1\r\n\r\n")
+            .unwrap()
+            .is_empty());
+
+        // HTTP/1.0 closes unless asked to keep the connection alive
+        let mut connection = Connection::default();
+        let requests = connection
+            .push(b"GET /a HTTP/1.0\r\nConnection: keep-alive\r\n\r\nGET /b HTTP/1.0\r\n\r\nGET /c HTTP/1.0\r\n\r\n")
+            .unwrap();
+        assert_eq!(lines(&requests), vec!["GET /a HTTP/1.0", "GET /b HTTP/1.0"]);
+        assert!(connection.is_closed());
+    }
+
+    #[test]
+    fn test_empty_line_after_body() {
+        let mut connection = Connection::default();
+        let requests = connection
+            .push(b"POST /a HTTP/1.1\r\nContent-Length: 2\r\n\r\nab\r\n")
+            .unwrap();
+        assert_eq!(lines(&requests), vec!["POST /a HTTP/1.1"]);
+        let requests = connection.push(b"GET /b HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(lines(&requests), vec!["GET /b HTTP/1.1"]);
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let mut connection = Connection::default();
+        let requests = connection
+            .push(b"GET /chat HTTP/1.1\r\nHost: x\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\r\n")
+            .unwrap();
+        assert_eq!(lines(&requests), vec!["GET /chat HTTP/1.1"]);
+        assert!(requests[0].is_upgrade());
+        assert!(connection.is_upgraded());
+        // websocket frames are not parsed as requests
+        assert!(connection.push(b"\x81\x00").unwrap().is_empty());
+        assert!(connection
+            .push(b"GET / HTTP/1.1\r\n\r\n")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_pending() {
+        let mut connection = Connection::new(ParseOptions::default());
+        assert!(connection.push(b"GET / HTTP/1.1\r\nHo").unwrap().is_empty());
+        assert_eq!(connection.pending().raw, b"GET / HTTP/1.1\r\nHo".to_vec());
+        assert_eq!(
+            connection.push(b"st x\r\n\r\n").unwrap_err(),
+            Errors::HeaderKeyWhitespace
+        );
+    }
+}
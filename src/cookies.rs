@@ -0,0 +1,257 @@
+use crate::errors::Errors;
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc6265#section-5.4
+
+    cookie-header = "Cookie:" OWS cookie-string OWS
+    cookie-string = cookie-pair *( ";" SP cookie-pair )
+
+    https://datatracker.ietf.org/doc/html/rfc6265#section-4.1.1
+
+    cookie-pair       = cookie-name "=" cookie-value
+    cookie-value      = *cookie-octet / ( DQUOTE *cookie-octet DQUOTE )
+*/
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Cookies {
+    pub values: Vec<(String, String)>,
+}
+
+impl Cookies {
+    pub fn parse(cookie_string: &str) -> Result<Self, Errors> {
+        let mut cookies = Cookies::default();
+        for pair in cookie_string.split(';') {
+            let pair = pair.trim_matches(|c| c == ' ' || c == '\t');
+            if pair.is_empty() {
+                continue;
+            }
+            // a pair without "=" is treated as a name with an empty value
+            let (name, value) = match pair.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => (pair, ""),
+            };
+            if name.is_empty() {
+                return Err(Errors::CookieNameIsEmpty);
+            }
+            // the DQUOTEs are not part of the value
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            cookies.values.push((name.to_owned(), value.to_owned()));
+        }
+        Ok(cookies)
+    }
+
+    // duplicate names are kept in order, the first one wins on lookup
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc6265#section-4.1
+
+    set-cookie-string = cookie-pair *( ";" SP cookie-av )
+    cookie-av         = expires-av / max-age-av / domain-av /
+                        path-av / secure-av / httponly-av /
+                        extension-av
+*/
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SetCookie {
+    pub name: String,
+    pub value: String,
+    pub expires: Option<String>,
+    pub max_age: Option<i64>,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+}
+
+impl SetCookie {
+    pub fn parse(value: &str) -> Result<Self, Errors> {
+        let mut parts = value.split(';');
+
+        let pair = parts.next().unwrap_or_default();
+        let (name, value) = match pair.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => {
+                return Err(Errors::Header(
+                    "Set-Cookie must start with a name=value pair".to_owned(),
+                ))
+            }
+        };
+        if name.is_empty() {
+            return Err(Errors::CookieNameIsEmpty);
+        }
+
+        let mut cookie = SetCookie {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            ..Default::default()
+        };
+
+        // unrecognized or malformed attributes are ignored (rfc6265 section 5.2)
+        for attribute in parts {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "expires" => cookie.expires = Some(value.to_owned()),
+                "max-age" => {
+                    if let Ok(max_age) = value.parse::<i64>() {
+                        cookie.max_age = Some(max_age);
+                    }
+                }
+                "domain" => cookie.domain = Some(value.trim_start_matches('.').to_owned()),
+                "path" => cookie.path = Some(value.to_owned()),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => {
+                    cookie.same_site = match value.to_ascii_lowercase().as_str() {
+                        "strict" => Some(SameSite::Strict),
+                        "lax" => Some(SameSite::Lax),
+                        "none" => Some(SameSite::None),
+                        _ => cookie.same_site,
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(cookie)
+    }
+
+    pub fn to_header_value(&self) -> String {
+        let mut value = format!("{}={}", self.name, self.value);
+        if let Some(expires) = &self.expires {
+            value.push_str(&format!("; Expires={}", expires));
+        }
+        if let Some(max_age) = self.max_age {
+            value.push_str(&format!("; Max-Age={}", max_age));
+        }
+        if let Some(domain) = &self.domain {
+            value.push_str(&format!("; Domain={}", domain));
+        }
+        if let Some(path) = &self.path {
+            value.push_str(&format!("; Path={}", path));
+        }
+        if self.secure {
+            value.push_str("; Secure");
+        }
+        if self.http_only {
+            value.push_str("; HttpOnly");
+        }
+        if let Some(same_site) = self.same_site {
+            let same_site = match same_site {
+                SameSite::Strict => "Strict",
+                SameSite::Lax => "Lax",
+                SameSite::None => "None",
+            };
+            value.push_str(&format!("; SameSite={}", same_site));
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let c = Cookies::parse("SID=31d4d96e407aad42; lang=en-US").unwrap();
+        assert_eq!(c.len(), 2);
+        assert_eq!(c.get("SID"), Some("31d4d96e407aad42"));
+        assert_eq!(c.get("lang"), Some("en-US"));
+        assert_eq!(c.get("missing"), None);
+    }
+
+    #[test]
+    fn test_duplicate_and_missing_equals() {
+        let c = Cookies::parse("a=1; flag; a=2").unwrap();
+        assert_eq!(c.len(), 3);
+        assert_eq!(c.get("a"), Some("1"));
+        assert_eq!(c.get("flag"), Some(""));
+    }
+
+    #[test]
+    fn test_values() {
+        // only the first "=" separates the name from the value
+        let c = Cookies::parse("token=YWJjZA==; quoted=\"abc\"; empty=; half=\"x").unwrap();
+        assert_eq!(
+            c.values,
+            vec![
+                ("token".to_owned(), "YWJjZA==".to_owned()),
+                ("quoted".to_owned(), "abc".to_owned()),
+                ("empty".to_owned(), "".to_owned()),
+                ("half".to_owned(), "\"x".to_owned()),
+            ]
+        );
+        assert_eq!(Cookies::parse("q=\"\"").unwrap().get("q"), Some(""));
+    }
+
+    #[test]
+    fn test_empty_name() {
+        assert_eq!(Cookies::parse("=value"), Err(Errors::CookieNameIsEmpty));
+    }
+
+    #[test]
+    fn test_set_cookie() {
+        let c = SetCookie::parse(
+            "SID=31d4; Path=/; Domain=.example.com; Max-Age=3600; Secure; httponly; SameSite=lax; Unknown=1",
+        )
+        .unwrap();
+        assert_eq!(c.name, "SID");
+        assert_eq!(c.value, "31d4");
+        assert_eq!(c.path, Some("/".to_owned()));
+        assert_eq!(c.domain, Some("example.com".to_owned()));
+        assert_eq!(c.max_age, Some(3600));
+        assert_eq!(c.expires, None);
+        assert!(c.secure);
+        assert!(c.http_only);
+        assert_eq!(c.same_site, Some(SameSite::Lax));
+        assert_eq!(
+            c.to_header_value(),
+            "SID=31d4; Max-Age=3600; Domain=example.com; Path=/; Secure; HttpOnly; SameSite=Lax"
+        );
+        assert_eq!(SetCookie::parse(&c.to_header_value()), Ok(c));
+    }
+
+    #[test]
+    fn test_set_cookie_invalid() {
+        assert_eq!(
+            SetCookie::parse("novalue; Secure"),
+            Err(Errors::Header(
+                "Set-Cookie must start with a name=value pair".to_owned()
+            ))
+        );
+        assert_eq!(SetCookie::parse("=1"), Err(Errors::CookieNameIsEmpty));
+
+        let c =
+            SetCookie::parse("a=b; Max-Age=soon; Expires=Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(c.max_age, None);
+        assert_eq!(c.expires, Some("Wed, 21 Oct 2015 07:28:00 GMT".to_owned()));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::errors::Errors;
+use base64::Engine;
+use encoding::label::encoding_from_whatwg_label;
+use encoding::DecoderTrap;
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc2047#section-2
+
+    encoded-word = "=?" charset "?" encoding "?" encoded-text "?="
+
+    An 'encoded-word' may not be more than 75 characters long, including 'charset',
+    'encoding', 'encoded-text', and delimiters.
+
+    https://datatracker.ietf.org/doc/html/rfc2047#section-6.2
+
+    When displaying a particular header field that contains multiple 'encoded-word's,
+    any 'linear-white-space' that separates a pair of adjacent 'encoded-word's is ignored.
+*/
+
+// the byte range of an encoded-word within a header value, delimiters included
+#[derive(Debug, Clone, PartialEq)]
+struct Point {
+    s: usize,
+    e: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct EncodedWord {
+    charset: String,
+    encoding: char,
+    text: String,
+}
+
+impl EncodedWord {
+    fn decode(&self) -> Result<String, Errors> {
+        let bytes = match self.encoding {
+            'B' | 'b' => match base64::engine::general_purpose::STANDARD.decode(&self.text) {
+                Ok(bytes) => bytes,
+                Err(_) => return Err(Errors::EncodedWord("invalid base64")),
+            },
+            'Q' | 'q' => decode_q(&self.text)?,
+            _ => return Err(Errors::EncodedWord("unknown encoding")),
+        };
+
+        // rfc2231 allows a language after the charset: "=?us-ascii*en?q?...?="
+        let charset = self.charset.split('*').next().unwrap_or_default();
+        let charset = match encoding_from_whatwg_label(charset) {
+            Some(charset) => charset,
+            None => return Err(Errors::EncodedWord("unknown charset")),
+        };
+        match charset.decode(&bytes, DecoderTrap::Strict) {
+            Ok(decoded) => Ok(decoded),
+            Err(_) => Err(Errors::EncodedWord("text is not valid in its charset")),
+        }
+    }
+}
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc2047#section-4.2
+
+    The "Q" encoding is similar to the "Quoted-Printable" content-transfer-encoding,
+    "=" followed by two hexadecimal digits is an octet and "_" is a space.
+*/
+fn decode_q(text: &str) -> Result<Vec<u8>, Errors> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'=' => {
+                let hex = match text.get(i + 1..i + 3) {
+                    Some(hex) => hex,
+                    None => return Err(Errors::EncodedWord("truncated escape")),
+                };
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => decoded.push(byte),
+                    Err(_) => return Err(Errors::EncodedWord("invalid escape")),
+                }
+                i += 3;
+            }
+            b'_' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Ok(decoded)
+}
+
+fn find_encoded_words(value: &str) -> Vec<Point> {
+    let mut points = vec![];
+    let mut at = 0;
+    while let Some(start) = value[at..].find("=?").map(|i| at + i) {
+        let end = word_end(value, start).filter(|end| split_word(&value[start..*end]).is_some());
+        match end {
+            Some(end) => {
+                points.push(Point { s: start, e: end });
+                at = end;
+            }
+            None => at = start + 1,
+        }
+    }
+    points
+}
+
+// the "?=" can only come after the charset and encoding, "=?utf-8?q?=C3=A9?=" has one earlier
+fn word_end(value: &str, start: usize) -> Option<usize> {
+    let rest = &value[start + 2..];
+    let charset_end = rest.find('?')?;
+    let encoding_end = charset_end + 1 + rest[charset_end + 1..].find('?')?;
+    let text_end = encoding_end + 1 + rest[encoding_end + 1..].find("?=")?;
+    Some(start + 2 + text_end + 2)
+}
+
+// charset, encoding and text of "=?charset?encoding?text?=" when it is well formed
+fn split_word(word: &str) -> Option<(&str, &str, &str)> {
+    let inner = word.strip_prefix("=?")?.strip_suffix("?=")?;
+    let (charset, rest) = inner.split_once('?')?;
+    let (encoding, text) = rest.split_once('?')?;
+    if charset.is_empty() || encoding.len() != 1 || text.contains('?') {
+        return None;
+    }
+    if word.contains(|c: char| c.is_ascii_whitespace()) {
+        return None;
+    }
+    Some((charset, encoding, text))
+}
+
+fn parse_encoded_words(value: &str, points: &[Point]) -> Vec<Option<EncodedWord>> {
+    points
+        .iter()
+        .map(|p| {
+            let (charset, encoding, text) = split_word(&value[p.s..p.e])?;
+            Some(EncodedWord {
+                charset: charset.to_owned(),
+                encoding: encoding.chars().next()?,
+                text: text.to_owned(),
+            })
+        })
+        .collect()
+}
+
+// decode every encoded-word in value, leaving everything else (and any encoded-word that
+// can't be decoded) untouched
+pub fn decode(value: &str) -> String {
+    let points = find_encoded_words(value);
+    let words = parse_encoded_words(value, &points);
+
+    let mut decoded = String::with_capacity(value.len());
+    let mut at = 0;
+    let mut previous_decoded = false;
+    for (point, word) in points.iter().zip(words.iter()) {
+        let between = &value[at..point.s];
+        let text = word.as_ref().and_then(|w| w.decode().ok());
+        let adjacent = previous_decoded && between.chars().all(|c| c.is_ascii_whitespace());
+        if !(adjacent && text.is_some()) {
+            decoded.push_str(between);
+        }
+        match &text {
+            Some(text) => decoded.push_str(text),
+            None => decoded.push_str(&value[point.s..point.e]),
+        }
+        previous_decoded = text.is_some();
+        at = point.e;
+    }
+    decoded.push_str(&value[at..]);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("plain text"), "plain text");
+        assert_eq!(decode("=?UTF-8?B?Y2Fmw6k=?="), "café");
+        assert_eq!(decode("=?iso-8859-1?q?caf=E9_au_lait?="), "café au lait");
+        assert_eq!(
+            decode("Hello =?utf-8?q?w=C3=B6rld?= and =?utf-8?b?YmV5b25k?=!"),
+            "Hello wörld and beyond!"
+        );
+        // whitespace between adjacent encoded-words is dropped
+        assert_eq!(decode("=?utf-8?q?a?= \t =?utf-8?q?b?="), "ab");
+        // encoded-words that can't be decoded are kept as they are
+        assert_eq!(
+            decode("=?x-unknown?q?a?= =?utf-8?q?b?="),
+            "=?x-unknown?q?a?= b"
+        );
+        assert_eq!(decode("=?utf-8?x?a?="), "=?utf-8?x?a?=");
+        assert_eq!(decode("=?utf-8?q?=C3=A9?="), "é");
+    }
+
+    #[test]
+    fn test_malformed_markers() {
+        // unterminated
+        assert_eq!(decode("=?utf-8?q?abc"), "=?utf-8?q?abc");
+        assert_eq!(decode("a =?foo"), "a =?foo");
+        assert!(find_encoded_words("=?utf-8?q?abc").is_empty());
+        // a lone end marker
+        assert_eq!(decode("a ?= b"), "a ?= b");
+        assert_eq!(decode("?= =?utf-8?q?x?="), "?= x");
+        // adjacent words
+        assert_eq!(decode("=?utf-8?q?a?==?utf-8?q?b?="), "ab");
+        // an extra '?' in the text is not an encoded-word
+        assert_eq!(decode("=?utf-8?q?a?b?="), "=?utf-8?q?a?b?=");
+        assert_eq!(
+            find_encoded_words("=?utf-8?q?a?b?= =?utf-8?q?c?="),
+            vec![Point { s: 16, e: 29 }]
+        );
+        // a start marker inside a word that is not well formed
+        assert_eq!(decode("=?=?utf-8?q?a?="), "=?a");
+
+        for value in ["=?", "?=", "=?=", "=??=", "=?a?b?=", "=?a??c?="] {
+            for point in find_encoded_words(value) {
+                assert!(point.s < point.e && point.e <= value.len());
+            }
+            decode(value);
+        }
+    }
+}
@@ -1,12 +1,225 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Errors<'a> {
+pub enum Errors {
     HeaderIndexOutOfBounds,
     HeaderKeyWhitespace,
     HeaderNonAsciiByteAt(usize),
+    HeaderIllegalByteAt(usize),
+    HeaderInvalidKeyChar(usize),
     HeaderIsEmpty,
+    // a header line without the colon that ends its name
+    HeaderMissingColon,
     HeaderFromUtf8(std::string::FromUtf8Error),
     CannotFillHeaders,
-    Header(&'a str),
+    Header(String),
     Parse(std::string::FromUtf8Error),
-    ContentLength(std::num::ParseIntError),
+    // an integer that failed to parse, Content-Length values are InvalidContentLength
+    ParseInt(std::num::ParseIntError),
+    CookieNameIsEmpty,
+    PercentDecode,
+    RequestLine(&'static str),
+    BareLineFeed,
+    BareCarriageReturn,
+    Chunk(&'static str),
+    UnknownExpectValue(String),
+    EncodedWord(&'static str),
+    QualityValue(String),
+    MediaRange(String),
+    BodyTaken,
+    // a header continued on the next line, only refused by strict requests
+    ObsoleteLineFolding,
+    // the Content-Length value as it was received
+    InvalidContentLength(String),
+    ContentLengthTooLarge(usize),
+    // Json and JsonSchema are only returned with the serde_json and schemars features but
+    // are always part of the enum, so a match on Errors compiles whatever features are on
+    Json(String),
+    // a JSON pointer to the invalid value and what jsonschema says is wrong with it
+    JsonSchema(String),
+    // the reader ended before the request was complete
+    UnexpectedEof,
+    // a failed read, as the io::Error displayed
+    Io(String),
+    // a request that parsed but can't be used as it is, e.g. from_parts with an unfinished body
+    Invalid(ValidationIssue),
+}
+
+impl fmt::Display for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Errors::HeaderIndexOutOfBounds => write!(f, "header index out of bounds"),
+            Errors::HeaderKeyWhitespace => {
+                write!(f, "whitespace is not allowed in a header name")
+            }
+            Errors::HeaderNonAsciiByteAt(at) => {
+                write!(f, "header contains a non-ascii byte at {}", at)
+            }
+            Errors::HeaderIllegalByteAt(at) => {
+                write!(f, "header contains a control byte at {}", at)
+            }
+            Errors::HeaderInvalidKeyChar(at) => {
+                write!(
+                    f,
+                    "header name contains a byte that is not a tchar at {}",
+                    at
+                )
+            }
+            Errors::HeaderIsEmpty => write!(f, "header name is empty"),
+            Errors::HeaderMissingColon => write!(f, "header has no colon after its name"),
+            Errors::HeaderFromUtf8(e) => write!(f, "header is not valid utf8: {}", e),
+            Errors::CannotFillHeaders => write!(f, "the end of the headers has not been found"),
+            Errors::Header(message) => write!(f, "{}", message),
+            Errors::Parse(e) => write!(f, "not valid utf8: {}", e),
+            Errors::ParseInt(e) => write!(f, "invalid integer: {}", e),
+            Errors::CookieNameIsEmpty => write!(f, "cookie name is empty"),
+            Errors::PercentDecode => write!(f, "invalid percent-encoding"),
+            Errors::RequestLine(message) => write!(f, "invalid request line: {}", message),
+            Errors::BareLineFeed => write!(f, "line feed without a preceding carriage return"),
+            Errors::BareCarriageReturn => {
+                write!(f, "carriage return without a following line feed")
+            }
+            Errors::Chunk(message) => write!(f, "invalid chunked body: {}", message),
+            Errors::UnknownExpectValue(value) => write!(f, "unknown Expect value: {}", value),
+            Errors::EncodedWord(message) => write!(f, "invalid encoded-word: {}", message),
+            Errors::QualityValue(value) => write!(f, "invalid quality value: {}", value),
+            Errors::MediaRange(value) => write!(f, "invalid media range: {}", value),
+            Errors::BodyTaken => write!(f, "the body has been taken from the request"),
+            Errors::ObsoleteLineFolding => write!(f, "header line folding is not allowed"),
+            Errors::InvalidContentLength(value) => {
+                write!(f, "invalid Content-Length: {:?}", value)
+            }
+            Errors::ContentLengthTooLarge(length) => {
+                write!(f, "Content-Length {} is larger than allowed", length)
+            }
+            Errors::Json(message) => write!(f, "invalid json: {}", message),
+            Errors::JsonSchema(message) => {
+                write!(f, "json does not match its schema: {}", message)
+            }
+            Errors::UnexpectedEof => {
+                write!(f, "the connection closed before the request was complete")
+            }
+            Errors::Io(message) => write!(f, "io error: {}", message),
+            Errors::Invalid(issue) => write!(f, "invalid request: {}", issue),
+        }
+    }
+}
+
+// problems found by Request::validate_request in a request that otherwise parsed fine
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    // a header that may only be sent once, by its lowercased name
+    DuplicateHeader(String),
+    // the body has not been fully received yet
+    Incomplete,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationIssue::DuplicateHeader(name) => write!(f, "duplicate header: {}", name),
+            ValidationIssue::Incomplete => write!(f, "the request is not complete"),
+        }
+    }
+}
+
+// lets ? convert the std errors that the parser runs into
+impl From<std::string::FromUtf8Error> for Errors {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        Errors::Parse(e)
+    }
+}
+
+impl From<std::num::ParseIntError> for Errors {
+    fn from(e: std::num::ParseIntError) -> Self {
+        Errors::ParseInt(e)
+    }
+}
+
+// NOTE: From<Errors> for Box<dyn std::error::Error> comes from the blanket impl in std
+// since Errors implements std::error::Error, so it cannot (and need not) be written here
+impl std::error::Error for Errors {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Errors::HeaderFromUtf8(e) => Some(e),
+            Errors::Parse(e) => Some(e),
+            Errors::ParseInt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    fn boxed() -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err(Errors::Header(
+            "Content-Length header must appear only once".to_owned(),
+        ))?;
+        Ok(())
+    }
+
+    fn from_std(raw: Vec<u8>, length: &str) -> Result<(String, usize), Errors> {
+        Ok((String::from_utf8(raw)?, length.parse::<usize>()?))
+    }
+
+    fn boxed_dyn() -> Result<(), Box<dyn Error>> {
+        from_std(vec![b'a'], "x")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_from() {
+        assert_eq!(from_std(vec![b'a'], "1"), Ok(("a".to_owned(), 1)));
+        assert!(matches!(from_std(vec![0xff], "1"), Err(Errors::Parse(_))));
+        assert!(matches!(
+            from_std(vec![b'a'], "x"),
+            Err(Errors::ParseInt(_))
+        ));
+        assert_eq!(
+            boxed_dyn().unwrap_err().to_string(),
+            "invalid integer: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            Errors::HeaderNonAsciiByteAt(3).to_string(),
+            "header contains a non-ascii byte at 3"
+        );
+        assert_eq!(
+            Errors::HeaderIllegalByteAt(9).to_string(),
+            "header contains a control byte at 9"
+        );
+        assert_eq!(
+            Errors::Json("expected value at line 1 column 1".to_owned()).to_string(),
+            "invalid json: expected value at line 1 column 1"
+        );
+        assert_eq!(
+            Errors::Io("broken pipe".to_owned()).to_string(),
+            "io error: broken pipe"
+        );
+        assert_eq!(
+            Errors::Chunk("missing CRLF").to_string(),
+            "invalid chunked body: missing CRLF"
+        );
+        let e = Errors::Parse(String::from_utf8(vec![0xff]).unwrap_err());
+        assert_eq!(
+            e.to_string(),
+            "not valid utf8: invalid utf-8 sequence of 1 bytes from index 0"
+        );
+    }
+
+    #[test]
+    fn test_boxed_error() {
+        let e = boxed().unwrap_err();
+        assert_eq!(e.to_string(), "Content-Length header must appear only once");
+
+        let e = Errors::ParseInt("x".parse::<usize>().unwrap_err());
+        assert!(e.source().is_some());
+        assert!(Errors::BareLineFeed.source().is_none());
+    }
 }
@@ -0,0 +1,194 @@
+use crate::errors::Errors;
+use crate::{ParseOptions, Request};
+use std::collections::VecDeque;
+
+/*
+    https://www.rfc-editor.org/rfc/rfc7230#section-6.3.2
+
+    A client that supports persistent connections MAY "pipeline" its
+    requests (i.e., send multiple requests without waiting for each
+    response).  A server MAY process a sequence of pipelined requests in
+    parallel if they all have safe methods, but it MUST send the
+    corresponding responses in the same order that the requests were
+    received.
+*/
+
+// splits the bytes read from one connection into the requests sent on it, in order
+// NOTE: ParseOptions::headers_only drops body bytes so it can't be used with a Framer
+#[derive(Debug, Clone, Default)]
+pub struct Framer {
+    // used for every request parsed from the stream
+    pub options: ParseOptions,
+    current: Request,
+    ready: VecDeque<Request>,
+    // set once an upgrade request has been handed out, see push
+    upgraded: bool,
+}
+
+impl Framer {
+    pub fn new(options: ParseOptions) -> Self {
+        Framer {
+            current: Request {
+                options: options.clone(),
+                ..Default::default()
+            },
+            options,
+            ready: VecDeque::new(),
+            upgraded: false,
+        }
+    }
+
+    // an upgrade request is ready as soon as its head is, so that it can be answered with a
+    // 101 (Switching Protocols), after it nothing more is framed since the rest of the
+    // connection belongs to the new protocol: the bytes that arrived with the head are at
+    // raw[header_len()..] of the request and push takes nothing from later data
+    pub fn push(&mut self, data: &[u8]) -> Result<(), Errors> {
+        if self.upgraded {
+            return Ok(());
+        }
+        let mut data = data.to_vec();
+        loop {
+            self.current.update_raw(&mut data)?;
+            if self.current.has_full_headers() && self.current.is_upgrade() {
+                self.upgraded = true;
+                let upgrade = std::mem::take(&mut self.current);
+                self.ready.push_back(upgrade);
+                return Ok(());
+            }
+            if !self.current.body_complete() {
+                return Ok(());
+            }
+
+            // anything past the end of the current message starts the next one
+            let consumed = self.current.consumed_len();
+            data = self.current.raw.split_off(consumed);
+
+            let next = Request {
+                options: self.options.clone(),
+                ..Default::default()
+            };
+            self.ready
+                .push_back(std::mem::replace(&mut self.current, next));
+            if data.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    // the oldest request that has been completely received
+    pub fn next_complete(&mut self) -> Option<Request> {
+        self.ready.pop_front()
+    }
+
+    // the request that is still being received
+    pub fn pending(&self) -> &Request {
+        &self.current
+    }
+
+    // true once an upgrade request has been handed out
+    pub fn is_upgraded(&self) -> bool {
+        self.upgraded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIPELINED: &[u8] = b"POST /a HTTP/1.1\r\nContent-Length: 3\r\n\r\nabcGET /b HTTP/1.1\r\n\r\nPOST /c HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\nGET /d HTTP/1.1\r\n";
+
+    fn assert_pipelined(framer: &mut Framer) {
+        let a = framer.next_complete().unwrap();
+        assert_eq!(a.request_line, "POST /a HTTP/1.1");
+        assert_eq!(a.body(), b"abc".to_vec());
+        assert_eq!(
+            a.dump(),
+            b"POST /a HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc".to_vec()
+        );
+
+        let b = framer.next_complete().unwrap();
+        assert_eq!(b.request_line, "GET /b HTTP/1.1");
+        assert_eq!(b.body(), b"".to_vec());
+
+        let c = framer.next_complete().unwrap();
+        assert_eq!(c.request_line, "POST /c HTTP/1.1");
+        assert_eq!(c.body(), b"hi".to_vec());
+
+        assert!(framer.next_complete().is_none());
+        assert_eq!(framer.pending().raw, b"GET /d HTTP/1.1\r\n".to_vec());
+    }
+
+    #[test]
+    fn test_pipelined() {
+        let mut framer = Framer::default();
+        assert_eq!(framer.push(PIPELINED), Ok(()));
+        assert_pipelined(&mut framer);
+    }
+
+    #[test]
+    fn test_pipelined_byte_at_a_time() {
+        let mut framer = Framer::default();
+        for byte in PIPELINED.iter() {
+            assert_eq!(framer.push(&[*byte]), Ok(()));
+        }
+        assert_pipelined(&mut framer);
+    }
+
+    #[test]
+    fn test_upgrade_keeps_the_rest() {
+        let mut framer = Framer::default();
+        let raw = b"GET /a HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nConnection: upgrade\r\nUpgrade: websocket\r\n\r\n\x81\x00";
+        assert_eq!(framer.push(&raw[..raw.len() - 2]), Ok(()));
+        assert_eq!(framer.push(&raw[raw.len() - 2..]), Ok(()));
+        assert!(framer.is_upgraded());
+        assert_eq!(
+            framer.next_complete().unwrap().request_line,
+            "GET /a HTTP/1.1"
+        );
+
+        // handed out before its frames arrive, which stay with the caller
+        let upgrade = framer.next_complete().unwrap();
+        assert!(upgrade.is_upgrade());
+        assert_eq!(upgrade.raw, raw[19..raw.len() - 2].to_vec());
+        assert!(framer.next_complete().is_none());
+
+        let mut framer = Framer::default();
+        assert_eq!(framer.push(&raw[19..]), Ok(()));
+        let upgrade = framer.next_complete().unwrap();
+        assert_eq!(
+            upgrade.raw[upgrade.header_len().unwrap()..],
+            b"\x81\x00"[..]
+        );
+        assert_eq!(framer.push(b"GET / HTTP/1.1\r\n\r\n"), Ok(()));
+        assert!(framer.next_complete().is_none());
+        assert!(framer.pending().raw.is_empty());
+    }
+
+    #[test]
+    fn test_empty_lines_between_requests() {
+        let mut framer = Framer::default();
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\nab\r\nGET / HTTP/1.1\r\n\r\n";
+        assert_eq!(framer.push(raw), Ok(()));
+        assert_eq!(framer.next_complete().unwrap().body(), b"ab".to_vec());
+        assert_eq!(
+            framer.next_complete().unwrap().request_line,
+            "GET / HTTP/1.1"
+        );
+        assert!(framer.next_complete().is_none());
+
+        // nothing but empty lines is no request at all
+        let mut framer = Framer::default();
+        assert_eq!(framer.push(b"\r\n\r\n"), Ok(()));
+        assert!(framer.next_complete().is_none());
+        assert!(framer.pending().raw.is_empty());
+    }
+
+    #[test]
+    fn test_error() {
+        let mut framer = Framer::default();
+        let res = framer.push(b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nfo o: x\r\n\r\n");
+        assert_eq!(res, Err(Errors::HeaderKeyWhitespace));
+        assert!(framer.next_complete().is_some());
+        assert!(framer.next_complete().is_none());
+    }
+}
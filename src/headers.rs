@@ -1,33 +1,245 @@
+use crate::cookies::Cookies;
+use crate::encoded_word;
 use crate::errors::Errors;
+use crate::intern::Interner;
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 
-#[derive(Debug, Default, Clone, PartialEq)]
+// header field names are case-insensitive (rfc7230 section 3.2) and may only contain
+// ASCII, so every comparison of header names goes through here: no allocation and no
+// unicode case folding that could make two different byte sequences compare equal
+pub fn name_eq(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc7230#section-3.2.6
+
+    token = 1*tchar
+    tchar = "!" / "#" / "$" / "%" / "&" / "'" / "*"
+          / "+" / "-" / "." / "^" / "_" / "`" / "|" / "~"
+          / DIGIT / ALPHA
+*/
+pub fn is_tchar(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+/*
+    https://www.rfc-editor.org/rfc/rfc7230#section-3.3.2
+
+    Content-Length = 1*DIGIT
+
+    If a message is received that has multiple Content-Length header fields with
+    field-values consisting of the same decimal value, or a single Content-Length
+    header field with a field value containing a list of identical decimal values
+    (e.g., "Content-Length: 42, 42"), indicating that duplicate Content-Length
+    header fields have been generated or combined by an upstream message processor,
+    then the recipient MUST either reject the message as invalid or replace the
+    duplicated field-values with a single valid Content-Length field containing
+    that decimal value prior to determining the message body length or forwarding
+    the message.
+*/
+// leading zeros are allowed by the grammar so "007" is 7, anything but digits is rejected
+pub fn parse_content_length(value: &str, max: usize) -> Result<usize, Errors> {
+    let invalid = || Errors::InvalidContentLength(value.to_owned());
+    let mut length = None;
+    for element in value.split(',') {
+        let element = element.trim_matches(|c| c == ' ' || c == '\t');
+        if element.is_empty() || !element.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let parsed = element.parse::<usize>().map_err(|_| invalid())?;
+        match length {
+            Some(length) if length != parsed => return Err(invalid()),
+            _ => length = Some(parsed),
+        }
+    }
+    match length {
+        Some(length) if length > max => Err(Errors::ContentLengthTooLarge(length)),
+        Some(length) => Ok(length),
+        None => Err(invalid()),
+    }
+}
+
+// headers carrying credentials or session state, which should be redacted before logging
+pub const SENSITIVE_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "www-authenticate",
+];
+
+fn check_byte(byte: u8, at: usize) -> Result<(), Errors> {
+    if byte > 127 {
+        return Err(Errors::HeaderNonAsciiByteAt(at));
+    }
+    // a CR or LF would end the header early when it is written out again, which
+    // lets whoever controls the value add headers of their own
+    if byte == b'\r' || byte == b'\n' || byte == 0 {
+        return Err(Errors::HeaderIllegalByteAt(at));
+    }
+    Ok(())
+}
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc7230#section-3.2
+
+    field-value    = *( field-content / obs-fold )
+    field-content  = field-vchar [ 1*( SP / HTAB ) field-vchar ]
+    field-vchar    = VCHAR / obs-text
+*/
+// on top of check_byte, values may not hold control characters other than HTAB
+fn check_value_byte(byte: u8, at: usize) -> Result<(), Errors> {
+    check_byte(byte, at)?;
+    if (byte < 0x20 && byte != b'\t') || byte == 0x7f {
+        return Err(Errors::HeaderIllegalByteAt(at));
+    }
+    Ok(())
+}
+
+// how much of RFC 7230 Header::new_with_validation_level holds a header line to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationLevel {
+    // every check, what Header::new does
+    #[default]
+    Strict,
+    // also accepts obs-text (bytes above 127) in the value, invalid utf8 is replaced
+    Lenient,
+    // !!! NO VALIDATION IS DONE !!! only for header lines that are known to be valid
+    None,
+}
+
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "HeaderFields", into = "HeaderFields")
+)]
 pub struct Header {
-    pub key: String,
+    pub key: Arc<str>,
     pub value: String,
     pub bytes: Vec<u8>,
 }
 
+// what a Header is serialized as, deserializing goes through from_parts so a header that
+// could not have been parsed is refused
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct HeaderFields {
+    key: String,
+    value: String,
+}
+
+#[cfg(feature = "serde")]
+impl From<Header> for HeaderFields {
+    fn from(h: Header) -> Self {
+        HeaderFields {
+            key: h.key.to_string(),
+            value: h.value,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<HeaderFields> for Header {
+    type Error = Errors;
+
+    fn try_from(fields: HeaderFields) -> Result<Self, Errors> {
+        Header::from_parts(&fields.key, &fields.value)
+    }
+}
+
 impl Header {
-    pub fn new(raw: Vec<u8>) -> Result<Self, Errors<'static>> {
+    pub fn new(raw: Vec<u8>) -> Result<Self, Errors> {
+        Header::new_with_validation_level(raw, ValidationLevel::Strict)
+    }
+
+    pub fn new_with_validation_level(raw: Vec<u8>, level: ValidationLevel) -> Result<Self, Errors> {
+        Header::parse(raw, None, level)
+    }
+
+    // the same as new but the name is shared with every other header the interner has seen
+    pub fn new_interned(raw: Vec<u8>, interner: &mut Interner) -> Result<Self, Errors> {
+        Header::parse(raw, Some(interner), ValidationLevel::Strict)
+    }
+
+    /*
+        !!! NO VALIDATION IS DONE !!!
+
+        ONLY use this with names and values that are known to be valid, e.g. constants
+        compiled into a server. A name with whitespace or a value with a CR or LF in it
+        is written out as is by dump, which lets whoever controls it add headers of
+        their own. Use from_parts for anything that came from outside of the program.
+    */
+    #[cfg(feature = "unchecked")]
+    pub fn new_unchecked(key: String, value: String) -> Header {
+        let bytes = format!("{}: {}", key, value).into_bytes();
+        Header {
+            key: Arc::from(key),
+            value,
+            bytes,
+        }
+    }
+
+    // the same checks as new for a name and value that are already split, the value's
+    // leading spaces are dropped just like they are after the colon
+    pub fn from_parts(key: &str, value: &str) -> Result<Self, Errors> {
+        if key.is_empty() {
+            return Err(Errors::HeaderIsEmpty);
+        }
+        for (i, byte) in key.bytes().enumerate() {
+            check_byte(byte, i)?;
+            if byte == b' ' || byte == b'\t' {
+                return Err(Errors::HeaderKeyWhitespace);
+            }
+            if !is_tchar(byte) {
+                return Err(Errors::HeaderInvalidKeyChar(i));
+            }
+        }
+        // error positions are reported as if the header was given to new as "key: value"
+        let offset = key.len() + 2;
+        for (i, byte) in value.bytes().enumerate() {
+            check_value_byte(byte, offset + i)?;
+        }
+
+        let bytes = format!("{}: {}", key, value).into_bytes();
+        Ok(Header {
+            key: Arc::from(key),
+            value: value.trim_start_matches(' ').to_owned(),
+            bytes,
+        })
+    }
+
+    fn parse(
+        raw: Vec<u8>,
+        interner: Option<&mut Interner>,
+        level: ValidationLevel,
+    ) -> Result<Self, Errors> {
+        if level == ValidationLevel::None {
+            return Ok(Header::split(raw, interner));
+        }
         let mut key: &[u8] = &[];
         let mut value: &[u8] = &[];
 
         for i in 0..raw.len() {
             let byte = raw[i];
 
-            if byte > 127 {
-                return Err(Errors::HeaderNonAsciiByteAt(i));
-            }
-            if key.len() > 0 {
+            if !key.is_empty() {
+                // obs-text: https://datatracker.ietf.org/doc/html/rfc7230#section-3.2.4
+                if !(byte > 127 && level == ValidationLevel::Lenient) {
+                    check_value_byte(byte, i)?;
+                }
                 // trim value's leading whitespace
-                if value.len() == 0 && byte != b' ' {
+                if value.is_empty() && byte != b' ' {
                     value = &raw[i..];
                 }
             } else {
+                check_byte(byte, i)?;
                 if byte == b':' {
                     key = &raw[0..i];
-                    if key.len() == 0 {
+                    if key.is_empty() {
                         return Err(Errors::HeaderIsEmpty);
                     }
                 } else if byte == b' ' || byte == b'\t' {
@@ -42,52 +254,302 @@ impl Header {
                         of 400 (Bad Request).
                     */
                     return Err(Errors::HeaderKeyWhitespace);
+                } else if !is_tchar(byte) {
+                    // field-name = token
+                    return Err(Errors::HeaderInvalidKeyChar(i));
                 }
             }
         }
 
-        let key = match String::from_utf8(key.to_owned()) {
-            Ok(s) => Ok(s),
-            Err(e) => Err(Errors::HeaderFromUtf8(e)),
-        }?;
+        // the loop only sets key once it reaches the colon
+        if key.is_empty() {
+            return Err(Errors::HeaderMissingColon);
+        }
 
-        let value = match String::from_utf8(value.to_owned()) {
-            Ok(s) => Ok(s),
-            Err(e) => Err(Errors::HeaderFromUtf8(e)),
+        // every byte is ascii at this point so this borrows rather than allocates
+        let key = String::from_utf8_lossy(key);
+        let key = match interner {
+            Some(interner) => interner.intern(&key),
+            None => Arc::from(key.as_ref()),
+        };
+
+        // obs-text is usually latin-1 rather than utf8, bytes keeps it as it was received
+        let value = match (String::from_utf8(value.to_owned()), level) {
+            (Ok(s), _) => Ok(s),
+            (Err(_), ValidationLevel::Lenient) => Ok(String::from_utf8_lossy(value).into_owned()),
+            (Err(e), _) => Err(Errors::HeaderFromUtf8(e)),
         }?;
 
         Ok(Header {
-            key: key.to_owned(),
+            key,
             value: value.to_owned(),
             bytes: raw.to_vec(),
         })
     }
+
+    // ValidationLevel::None: the name is everything before the first colon
+    fn split(raw: Vec<u8>, interner: Option<&mut Interner>) -> Self {
+        let colon = raw.iter().position(|b| *b == b':').unwrap_or(raw.len());
+        let key = String::from_utf8_lossy(&raw[..colon]);
+        let key = match interner {
+            Some(interner) => interner.intern(&key),
+            None => Arc::from(key.as_ref()),
+        };
+        let value = raw.get(colon + 1..).unwrap_or_default();
+        let value = String::from_utf8_lossy(value)
+            .trim_start_matches(' ')
+            .to_owned();
+        Header {
+            key,
+            value,
+            bytes: raw,
+        }
+    }
 }
 
+impl Header {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn name_is(&self, name: &str) -> bool {
+        name_eq(&self.key, name)
+    }
+
+    pub fn is_sensitive(&self) -> bool {
+        SENSITIVE_HEADERS.iter().any(|name| self.name_is(name))
+    }
+
+    // the value with any rfc2047 encoded-words ("=?UTF-8?B?Y2Fmw6k=?=") decoded
+    pub fn decode_value(&self) -> String {
+        encoded_word::decode(&self.value)
+    }
+
+    /*
+        https://datatracker.ietf.org/doc/html/rfc7230#section-3.2.4
+
+        The field value does not include any leading or trailing whitespace:
+        OWS occurring before the first non-whitespace octet of the field value
+        or after the last non-whitespace octet of the field value ought to be
+        excluded by parsers when extracting the field value from a header field.
+    */
+    pub fn trimmed_value(&self) -> &str {
+        self.value.trim_matches(|c| c == ' ' || c == '\t')
+    }
+
+    /*
+        https://datatracker.ietf.org/doc/html/rfc7230#section-7
+
+        #element => [ ( "," / element ) *( OWS "," [ OWS element ] ) ]
+
+        quoted-string  = DQUOTE *( qdtext / quoted-pair ) DQUOTE
+        quoted-pair    = "\" ( HTAB / SP / VCHAR / obs-text )
+
+        Commas inside of a quoted-string do not split the list.  The quotes are removed
+        and quoted-pairs are unescaped, empty elements are ignored.
+    */
+    pub fn value_list(&self) -> Vec<String> {
+        let mut elements = vec![];
+        let mut element = String::new();
+        let mut in_quotes = false;
+
+        let mut chars = self.value.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                '\\' if in_quotes => {
+                    if let Some(escaped) = chars.next() {
+                        element.push(escaped);
+                    }
+                }
+                ',' if !in_quotes => {
+                    elements.push(std::mem::take(&mut element));
+                }
+                c => element.push(c),
+            }
+        }
+        elements.push(element);
+
+        elements
+            .iter()
+            .map(|e| e.trim_matches(|c| c == ' ' || c == '\t').to_owned())
+            .filter(|e| !e.is_empty())
+            .collect()
+    }
+
+    /*
+        https://datatracker.ietf.org/doc/html/rfc7231#section-3.1.1.1
+
+        media-type = type "/" subtype *( OWS ";" OWS parameter )
+        parameter  = token "=" ( token / quoted-string )
+
+        Returns the value before the first ";" and its parameters.  Parameter names are
+        case-insensitive so they are lowercased, the first occurrence of a name wins.
+    */
+    pub fn value_params(&self) -> (String, HashMap<String, String>) {
+        let ows = |c| c == ' ' || c == '\t';
+        let mut parts = split_outside_quotes(&self.value, ';').into_iter();
+        let base = parts
+            .next()
+            .unwrap_or_default()
+            .trim_matches(ows)
+            .to_owned();
+
+        let mut params = HashMap::new();
+        for param in parts {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim_matches(ows), unquote(value.trim_matches(ows))),
+                None => (param.trim_matches(ows), String::new()),
+            };
+            if !name.is_empty() {
+                params.entry(name.to_ascii_lowercase()).or_insert(value);
+            }
+        }
+        (base, params)
+    }
+}
+
+pub(crate) fn split_outside_quotes(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c == separator && !in_quotes => {
+                parts.push(&value[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+// remove the surrounding DQUOTEs of a quoted-string and unescape its quoted-pairs
+pub(crate) fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => {
+            let mut unquoted = String::with_capacity(inner.len());
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some(escaped) = chars.next() {
+                            unquoted.push(escaped);
+                        }
+                    }
+                    c => unquoted.push(c),
+                }
+            }
+            unquoted
+        }
+        None => value.to_owned(),
+    }
+}
+
+// names are compared case-insensitively and values exactly, bytes (the line as it was
+// received) is not compared so "host: x" and "Host:x" are the same header
+impl PartialEq for Header {
+    fn eq(&self, other: &Self) -> bool {
+        self.name_is(&other.key) && self.value == other.value
+    }
+}
+
+impl Eq for Header {}
+
 impl fmt::Display for Header {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}: {}", self.key, self.value)
     }
 }
 
+/*
+    https://www.rfc-editor.org/rfc/rfc7231#section-7.1.1.1
+
+    IMF-fixdate  = day-name "," SP date1 SP time-of-day SP GMT
+    ; fixed length/zone/capitalization subset of the format
+    ; see Section 3.3 of [RFC5322]
+
+    Sun, 06 Nov 1994 08:49:37 GMT
+*/
+pub fn http_date(time: std::time::SystemTime) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    // times before the epoch are clamped to it
+    let seconds = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days = seconds / 86400;
+    let seconds = seconds % 86400;
+
+    // civil from days: https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+// serialized as a plain list of headers
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct Headers {
     pub values: Vec<Header>,
 }
 
 impl Headers {
-    pub fn add(&mut self, key: String, value: String) -> Result<(), Errors<'static>> {
-        let h = Header::new(format!("{}: {}", key, value).as_bytes().to_vec())?;
-        self.values.push(h);
+    pub fn add(&mut self, key: String, value: String) -> Result<(), Errors> {
+        self.values.push(Header::from_parts(&key, &value)?);
         Ok(())
     }
 
-    pub fn set(&mut self, index: usize, key: String, value: String) -> Result<(), Errors<'static>> {
+    // the same as add but any CR, LF or NUL byte is percent-encoded instead of rejected
+    pub fn add_sanitized(&mut self, key: &str, value: &str) -> Result<(), Errors> {
+        let sanitize = |s: &str| {
+            s.replace('\r', "%0D")
+                .replace('\n', "%0A")
+                .replace('\0', "%00")
+        };
+        self.add(sanitize(key), sanitize(value))
+    }
+
+    pub fn set(&mut self, index: usize, key: String, value: String) -> Result<(), Errors> {
         if index >= self.len() {
             return Err(Errors::HeaderIndexOutOfBounds);
         }
-        let h = Header::new(format!("{}: {}", key, value).as_bytes().to_vec())?;
-        self.values[index] = h;
+        self.values[index] = Header::from_parts(&key, &value)?;
         Ok(())
     }
 
@@ -95,30 +557,570 @@ impl Headers {
         if index >= self.len() {
             return Err(Errors::HeaderIndexOutOfBounds);
         }
-        return Ok(self.values[index].clone());
+        Ok(self.values[index].clone())
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Header> {
+        self.values.iter()
+    }
+
+    // the names as they were received, repeated names included
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.iter().map(|h| h.key())
+    }
+
+    // HTTP/2 only allows lowercase names: https://www.rfc-editor.org/rfc/rfc7540#section-8.1.2
+    pub fn lowercase_all_keys(&mut self) {
+        for header in self.values.iter_mut() {
+            if header.key.bytes().any(|b| b.is_ascii_uppercase()) {
+                header.key = Arc::from(header.key.to_ascii_lowercase());
+            }
+            // bytes starts with the name, it is the raw header line
+            let len = header.key.len().min(header.bytes.len());
+            header.bytes[..len].make_ascii_lowercase();
+        }
+    }
+
+    pub fn sensitive_headers(&self) -> impl Iterator<Item = &Header> {
+        self.values.iter().filter(|h| h.is_sensitive())
     }
 
     pub fn len(&self) -> usize {
         self.values.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // header names are case-insensitive: https://datatracker.ietf.org/doc/html/rfc7230#section-3.2
+    pub fn get(&self, key: &str) -> Option<&Header> {
+        self.values.iter().find(|h| h.name_is(key))
+    }
+
+    // the first matching header line as it was parsed, name included and line ending left out,
+    // e.g. to verify a signature over it or to pass it on without re-encoding
+    // NOTE: a folded header (obs-fold) is the unfolded line with each fold replaced by a single
+    // space, so only an unfolded header line matches the received bytes
+    pub fn get_raw_bytes(&self, key: &str) -> Option<&[u8]> {
+        self.get(key).map(|h| h.bytes.as_slice())
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        self.values.iter().any(|h| h.name_is(key))
+    }
+
+    pub fn count(&self, key: &str) -> usize {
+        self.values.iter().filter(|h| h.name_is(key)).count()
+    }
+
+    pub fn get_all(&self, key: &str) -> Vec<&Header> {
+        self.values.iter().filter(|h| h.name_is(key)).collect()
+    }
+
+    /*
+        https://www.rfc-editor.org/rfc/rfc7230#section-3.2.2
+
+        A recipient MAY combine multiple header fields with the same field name into one
+        "field-name: field-value" pair, without changing the semantics of the message, by
+        appending each subsequent field value to the combined field value in order,
+        separated by a comma.
+
+        Note: In practice, the "Set-Cookie" header field ([RFC6265]) often appears
+        multiple times in a response message and does not use the list syntax,
+        violating the above requirements on multiple header fields with the same name.
+    */
+    // None for Set-Cookie, which can't be combined, use get_all for it instead
+    pub fn get_combined(&self, key: &str) -> Option<String> {
+        if name_eq(key, "set-cookie") {
+            return None;
+        }
+        let values = self
+            .get_all(key)
+            .iter()
+            .map(|h| h.trimmed_value())
+            .collect::<Vec<&str>>();
+        if values.is_empty() {
+            return None;
+        }
+        Some(values.join(", "))
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc7231#section-7.1.1.2
+    // any Date header already present is replaced
+    pub fn add_date_header(&mut self) {
+        self.values.retain(|h| !h.name_is("date"));
+        let date = http_date(std::time::SystemTime::now());
+        // the name and the date are plain ascii so this never fails
+        if let Ok(h) = Header::from_parts("Date", &date) {
+            self.values.push(h);
+        }
+    }
+
+    // every coding from every Transfer-Encoding header, lowercased and in the order applied
+    // https://www.rfc-editor.org/rfc/rfc7230#section-3.3.1
+    pub fn transfer_encodings(&self) -> Vec<String> {
+        self.get_all("transfer-encoding")
+            .iter()
+            .flat_map(|h| h.value_list())
+            .map(|coding| coding.to_ascii_lowercase())
+            .collect()
+    }
+
+    // None when there is no Content-Length, the same rules as parsing a request otherwise
+    pub fn content_length(&self) -> Option<Result<usize, Errors>> {
+        let mut found = self.get_all("content-length").into_iter();
+        let header = found.next()?;
+        if found.next().is_some() {
+            return Some(Err(Errors::Header(
+                "Content-Length header must appear only once".to_owned(),
+            )));
+        }
+        Some(parse_content_length(&header.value, usize::MAX))
+    }
+
+    // the lowercased names that appear more than once, in the order they first appeared
+    pub fn duplicate_names(&self) -> Vec<String> {
+        let mut seen: Vec<String> = vec![];
+        let mut duplicates: Vec<String> = vec![];
+        for header in self.values.iter() {
+            let name = header.key.to_ascii_lowercase();
+            if !seen.contains(&name) {
+                seen.push(name);
+            } else if !duplicates.contains(&name) {
+                duplicates.push(name);
+            }
+        }
+        duplicates
+    }
+
+    pub fn has_duplicate_names(&self) -> bool {
+        self.values
+            .iter()
+            .enumerate()
+            .any(|(i, h)| self.values[..i].iter().any(|other| h.name_is(&other.key)))
+    }
+
+    // multiple Cookie headers are combined as if they were sent as one
+    pub fn cookies(&self) -> Result<Cookies, Errors> {
+        let combined = self
+            .get_all("cookie")
+            .iter()
+            .map(|h| h.value.as_str())
+            .collect::<Vec<&str>>()
+            .join("; ");
+        Cookies::parse(&combined)
+    }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+    type Item = &'a Header;
+    type IntoIter = std::slice::Iter<'a, Header>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+impl IntoIterator for Headers {
+    type Item = Header;
+    type IntoIter = std::vec::IntoIter<Header>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+// the same headers in any order, a repeated header has to be repeated the same number of times
+impl PartialEq for Headers {
+    fn eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        let mut matched = vec![false; other.len()];
+        self.values.iter().all(|header| {
+            let found = other
+                .values
+                .iter()
+                .enumerate()
+                .position(|(i, h)| !matched[i] && h == header);
+            match found {
+                Some(i) => {
+                    matched[i] = true;
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+}
+
+impl Eq for Headers {}
+
+impl FromIterator<Header> for Headers {
+    fn from_iter<I: IntoIterator<Item = Header>>(iter: I) -> Self {
+        Headers {
+            values: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<Header> for Headers {
+    fn extend<I: IntoIterator<Item = Header>>(&mut self, iter: I) {
+        self.values.extend(iter)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_iter() {
+        let mut headers = Headers::default();
+        headers.add("Host".to_owned(), "x".to_owned()).unwrap();
+        headers
+            .add("Connection".to_owned(), "close".to_owned())
+            .unwrap();
+        headers.add("Accept".to_owned(), "*/*".to_owned()).unwrap();
+        assert_eq!(
+            headers.keys().collect::<Vec<&str>>(),
+            vec!["Host", "Connection", "Accept"]
+        );
+
+        let filtered = headers
+            .iter()
+            .filter(|h| !h.name_is("connection"))
+            .cloned()
+            .collect::<Headers>();
+        assert_eq!(
+            filtered.keys().collect::<Vec<&str>>(),
+            vec!["Host", "Accept"]
+        );
+
+        let mut trailers = Headers::default();
+        trailers
+            .add("Expires".to_owned(), "never".to_owned())
+            .unwrap();
+        let mut merged = filtered.clone();
+        merged.extend(trailers);
+        assert_eq!(merged.len(), 3);
+
+        let mut count = 0;
+        for header in &merged {
+            assert!(!header.value.is_empty());
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        let owned = merged.into_iter().map(|h| h.value).collect::<Vec<String>>();
+        assert_eq!(owned, vec!["x", "*/*", "never"]);
+    }
+
+    #[test]
+    fn test_lowercase_all_keys() {
+        let mut headers = Headers::default();
+        headers
+            .add("Content-Type".to_owned(), "Text/HTML".to_owned())
+            .unwrap();
+        headers.add("x-lower".to_owned(), "A".to_owned()).unwrap();
+        headers
+            .values
+            .push(Header::new(b"X-Raw:B".to_vec()).unwrap());
+        headers.lowercase_all_keys();
+        assert_eq!(
+            headers.keys().collect::<Vec<&str>>(),
+            vec!["content-type", "x-lower", "x-raw"]
+        );
+        assert_eq!(headers.values[0].value, "Text/HTML");
+        assert_eq!(headers.values[0].bytes, b"content-type: Text/HTML".to_vec());
+        assert_eq!(headers.values[2].bytes, b"x-raw:B".to_vec());
+    }
+
+    #[test]
+    fn test_sensitive_headers() {
+        let mut headers = Headers::default();
+        headers.add("Host".to_owned(), "x".to_owned()).unwrap();
+        headers
+            .add("authorization".to_owned(), "Basic eDp5".to_owned())
+            .unwrap();
+        headers.add("Cookie".to_owned(), "a=b".to_owned()).unwrap();
+        headers.add("X-Cookie".to_owned(), "c".to_owned()).unwrap();
+        headers
+            .add("PROXY-AUTHORIZATION".to_owned(), "z".to_owned())
+            .unwrap();
+        assert!(!headers.values[0].is_sensitive());
+        assert!(headers.values[1].is_sensitive());
+        assert_eq!(
+            headers
+                .sensitive_headers()
+                .map(|h| h.key())
+                .collect::<Vec<&str>>(),
+            vec!["authorization", "Cookie", "PROXY-AUTHORIZATION"]
+        );
+    }
+
     #[test]
     fn test_non_ascii() {
         let h = Header::new("foo: bär".as_bytes().to_vec());
         assert_eq!(Err(Errors::HeaderNonAsciiByteAt(6)), h);
     }
 
+    #[test]
+    fn test_header_injection() {
+        let h = Header::new(b"X-Name: x\r\nEvil: yes".to_vec());
+        assert_eq!(Err(Errors::HeaderIllegalByteAt(9)), h);
+        let h = Header::new(b"X-Name: x\0".to_vec());
+        assert_eq!(Err(Errors::HeaderIllegalByteAt(9)), h);
+        let h = Header::new(b"X\nName: x".to_vec());
+        assert_eq!(Err(Errors::HeaderIllegalByteAt(1)), h);
+
+        let mut headers = Headers::default();
+        assert_eq!(
+            headers.add("X-Name".to_owned(), "x\r\nEvil: yes".to_owned()),
+            Err(Errors::HeaderIllegalByteAt(9))
+        );
+        assert!(headers.is_empty());
+        headers.add("X-Name".to_owned(), "x".to_owned()).unwrap();
+        assert_eq!(
+            headers.set(0, "X-Name".to_owned(), "x\nEvil: yes".to_owned()),
+            Err(Errors::HeaderIllegalByteAt(9))
+        );
+        assert_eq!(headers.values[0].value, "x");
+
+        headers
+            .add_sanitized("X-Other", "x\r\nEvil: yes\0")
+            .unwrap();
+        assert_eq!(
+            headers.values[1].to_string(),
+            "X-Other: x%0D%0AEvil: yes%00"
+        );
+        assert!(headers.get("evil").is_none());
+    }
+
+    #[test]
+    fn test_validation_level() {
+        let raw = b"X-Name: caf\xe9".to_vec();
+        assert_eq!(
+            Header::new_with_validation_level(raw.clone(), ValidationLevel::Strict),
+            Err(Errors::HeaderNonAsciiByteAt(11))
+        );
+        assert_eq!(
+            Header::new(raw.clone()),
+            Err(Errors::HeaderNonAsciiByteAt(11))
+        );
+        let h = Header::new_with_validation_level(raw.clone(), ValidationLevel::Lenient).unwrap();
+        assert_eq!(h.value, "caf\u{fffd}");
+        assert_eq!(h.bytes, raw);
+        let h = Header::new_with_validation_level("X-Name: café".into(), ValidationLevel::Lenient)
+            .unwrap();
+        assert_eq!(h.value, "café");
+        // everything but obs-text is still checked
+        assert_eq!(
+            Header::new_with_validation_level(b"X-Name: a\r\n".to_vec(), ValidationLevel::Lenient),
+            Err(Errors::HeaderIllegalByteAt(9))
+        );
+        assert_eq!(
+            Header::new_with_validation_level(b"X Name: a".to_vec(), ValidationLevel::Lenient),
+            Err(Errors::HeaderKeyWhitespace)
+        );
+
+        let h =
+            Header::new_with_validation_level(b"X Name:  a\r\n".to_vec(), ValidationLevel::None)
+                .unwrap();
+        assert_eq!((h.key(), h.value.as_str()), ("X Name", "a\r\n"));
+        let h =
+            Header::new_with_validation_level(b"no colon".to_vec(), ValidationLevel::None).unwrap();
+        assert_eq!((h.key(), h.value.as_str()), ("no colon", ""));
+    }
+
+    #[test]
+    fn test_control_bytes() {
+        let h = Header::new(b"X-Name: a\x00b".to_vec());
+        assert_eq!(Err(Errors::HeaderIllegalByteAt(9)), h);
+        let h = Header::new(b"X-Name: a\x7fb".to_vec());
+        assert_eq!(Err(Errors::HeaderIllegalByteAt(9)), h);
+        let h = Header::new(b"X-Name: a\x0bb".to_vec());
+        assert_eq!(Err(Errors::HeaderIllegalByteAt(9)), h);
+        let h = Header::new(b"X-Name: a\tb".to_vec()).unwrap();
+        assert_eq!(h.value, "a\tb");
+        let h = Header::new(b"X-Foo(bar): a".to_vec());
+        assert_eq!(Err(Errors::HeaderInvalidKeyChar(5)), h);
+        assert_eq!(
+            Header::from_parts("X-Name", "a\x1bb"),
+            Err(Errors::HeaderIllegalByteAt(9))
+        );
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let cases = [
+            ("Content-Type", "text/html"),
+            ("Content-Type", "  text/html\t"),
+            ("Empty", ""),
+            ("", "value"),
+            ("fo o", "bar"),
+            ("foo\t", "bar"),
+            ("foo", "bär"),
+            ("föo", "bar"),
+            ("foo", "x\r\nEvil: yes"),
+        ];
+        for (key, value) in cases {
+            let joined = Header::new(format!("{}: {}", key, value).into_bytes());
+            assert_eq!(Header::from_parts(key, value), joined, "{:?}", (key, value));
+        }
+        assert_eq!(
+            Header::from_parts("a:b", "c"),
+            Err(Errors::HeaderInvalidKeyChar(1))
+        );
+    }
+
+    #[cfg(feature = "unchecked")]
+    #[test]
+    fn test_new_unchecked() {
+        let h = Header::new_unchecked("Content-Type".to_owned(), "text/html".to_owned());
+        assert_eq!(Ok(h), Header::new(b"Content-Type: text/html".to_vec()));
+        // nothing is rejected
+        let h = Header::new_unchecked("fo o".to_owned(), "x\r\n".to_owned());
+        assert_eq!(h.key(), "fo o");
+    }
+
+    #[test]
+    fn test_eq() {
+        let a = Header::new(b"Content-Type: text/html".to_vec()).unwrap();
+        assert_eq!(a, Header::new(b"content-type:text/html".to_vec()).unwrap());
+        assert_ne!(a, Header::new(b"Content-Type: Text/HTML".to_vec()).unwrap());
+
+        let headers = |lines: &[&str]| {
+            lines
+                .iter()
+                .map(|l| Header::new(l.as_bytes().to_vec()).unwrap())
+                .collect::<Headers>()
+        };
+        assert_eq!(
+            headers(&["Host: x", "Accept: a", "accept: b"]),
+            headers(&["ACCEPT: b", "host: x", "Accept: a"])
+        );
+        assert_ne!(
+            headers(&["Accept: a", "Accept: a", "Accept: b"]),
+            headers(&["Accept: a", "Accept: b", "Accept: b"])
+        );
+        assert_ne!(headers(&["Host: x"]), headers(&["Host: x", "Host: x"]));
+    }
+
+    #[test]
+    fn test_invalid_key_char() {
+        let h = Header::new(b"a,b: v".to_vec());
+        assert_eq!(Err(Errors::HeaderInvalidKeyChar(1)), h);
+        let h = Header::new(b"a(b): v".to_vec());
+        assert_eq!(Err(Errors::HeaderInvalidKeyChar(1)), h);
+        let h = Header::new(b"a\x01: v".to_vec());
+        assert_eq!(Err(Errors::HeaderInvalidKeyChar(1)), h);
+        // separators are fine in the value
+        let h = Header::new(b"X-Custom_Header: (a, b)".to_vec()).unwrap();
+        assert_eq!(h.key(), "X-Custom_Header");
+        assert_eq!(
+            Header::from_parts("a/b", "v"),
+            Err(Errors::HeaderInvalidKeyChar(1))
+        );
+    }
+
     #[test]
     fn test_whitespace_header_key() {
         let h = Header::new("fo o: bar".as_bytes().to_vec());
         assert_eq!(Err(Errors::HeaderKeyWhitespace), h);
     }
 
+    #[test]
+    fn test_get_raw_bytes() {
+        let mut headers = Headers::default();
+        headers
+            .values
+            .push(Header::new(b"Signature:\tkeyId=\"a\" ".to_vec()).unwrap());
+        headers
+            .values
+            .push(Header::new(b"signature: second".to_vec()).unwrap());
+        assert_eq!(
+            headers.get_raw_bytes("SIGNATURE"),
+            Some(&b"Signature:\tkeyId=\"a\" "[..])
+        );
+        assert_eq!(headers.get_raw_bytes("host"), None);
+    }
+
+    #[test]
+    fn test_trimmed_value() {
+        let h = Header::new("Content-Type: text/html \t \t".as_bytes().to_vec()).unwrap();
+        assert_eq!(h.value, "text/html \t \t");
+        assert_eq!(h.trimmed_value(), "text/html");
+
+        let h = Header::new("Content-Type:\t text/ html\t".as_bytes().to_vec()).unwrap();
+        assert_eq!(h.trimmed_value(), "text/ html");
+
+        let h = Header::new("Empty: \t ".as_bytes().to_vec()).unwrap();
+        assert_eq!(h.trimmed_value(), "");
+    }
+
+    #[test]
+    fn test_name_eq_is_ascii_only() {
+        assert!(name_eq("Content-Length", "content-length"));
+        assert!(name_eq("CONTENT-LENGTH", "content-length"));
+        assert!(!name_eq("Content-Length", "content_length"));
+        // unicode case folding is not applied to header names
+        assert!(!name_eq("straße", "STRASSE"));
+        assert!(!name_eq("\u{212a}ey", "key"));
+        assert!(!name_eq("ǅ", "ǆ"));
+
+        let h = Header::new("X-Custom: v".as_bytes().to_vec()).unwrap();
+        assert!(h.name_is("x-custom"));
+        assert!(!h.name_is("x-custom "));
+    }
+
+    #[test]
+    fn test_value_list() {
+        let h = Header::new("Transfer-Encoding: gzip, chunked".as_bytes().to_vec()).unwrap();
+        assert_eq!(h.value_list(), vec!["gzip", "chunked"]);
+
+        let h = Header::new("Connection: ,close ,, keep-alive\t,".as_bytes().to_vec()).unwrap();
+        assert_eq!(h.value_list(), vec!["close", "keep-alive"]);
+
+        let h = Header::new(
+            "Cache-Control: no-cache=\"Set-Cookie, Foo\", max-age=0"
+                .as_bytes()
+                .to_vec(),
+        )
+        .unwrap();
+        assert_eq!(
+            h.value_list(),
+            vec!["no-cache=Set-Cookie, Foo", "max-age=0"]
+        );
+
+        let h = Header::new("If-Match: \"a\\\"b, c\", \"d\"".as_bytes().to_vec()).unwrap();
+        assert_eq!(h.value_list(), vec!["a\"b, c", "d"]);
+
+        let h = Header::new("Transfer-Encoding: chunkednotreally".as_bytes().to_vec()).unwrap();
+        assert_eq!(h.value_list(), vec!["chunkednotreally"]);
+    }
+
+    #[test]
+    fn test_value_params() {
+        let h = Header::new(
+            "Content-Type: text/html; Charset=utf-8 ; boundary=\"a;b=\\\"c\\\"\"; flag; charset=latin1"
+                .as_bytes()
+                .to_vec(),
+        )
+        .unwrap();
+        let (base, params) = h.value_params();
+        assert_eq!(base, "text/html");
+        assert_eq!(params.len(), 3);
+        assert_eq!(params["charset"], "utf-8");
+        assert_eq!(params["boundary"], "a;b=\"c\"");
+        assert_eq!(params["flag"], "");
+
+        let h = Header::new("Content-Disposition: attachment".as_bytes().to_vec()).unwrap();
+        let (base, params) = h.value_params();
+        assert_eq!(base, "attachment");
+        assert!(params.is_empty());
+    }
+
     #[test]
     fn test_empty_header_key() {
         let mut h = Headers { values: vec![] };
@@ -126,6 +1128,28 @@ mod tests {
         assert_eq!(Err(Errors::HeaderIsEmpty), r);
     }
 
+    #[test]
+    fn test_missing_colon() {
+        for raw in [&b"foo"[..], b"", b"foo-bar"] {
+            assert_eq!(
+                Header::new(raw.to_vec()),
+                Err(Errors::HeaderMissingColon),
+                "{:?}",
+                raw
+            );
+        }
+        // nothing is validated, so the whole line is the name
+        let h = Header::new_with_validation_level(b"foo".to_vec(), ValidationLevel::None).unwrap();
+        assert_eq!(h.key.as_ref(), "foo");
+        assert_eq!(h.value, "");
+
+        let mut r = crate::Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"GET / HTTP/1.1\r\nfoo\r\n\r\n".to_vec()),
+            Err(Errors::HeaderMissingColon)
+        );
+    }
+
     #[test]
     fn test_index_out_of_bounds() {
         let mut h = Headers { values: vec![] };
@@ -133,4 +1157,175 @@ mod tests {
         h.add("A".to_owned(), "B".to_owned()).unwrap();
         assert_eq!(Err(Errors::HeaderIndexOutOfBounds), h.at(1));
     }
+
+    #[test]
+    fn test_cookies() {
+        let mut h = Headers { values: vec![] };
+        h.add("Cookie".to_owned(), "a=1; b=2".to_owned()).unwrap();
+        h.add("Host".to_owned(), "example.com".to_owned()).unwrap();
+        h.add("cookie".to_owned(), "a=3; c".to_owned()).unwrap();
+
+        let c = h.cookies().unwrap();
+        assert_eq!(c.len(), 4);
+        assert_eq!(c.get("a"), Some("1"));
+        assert_eq!(c.get("b"), Some("2"));
+        assert_eq!(c.get("c"), Some(""));
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let mut h = Headers::default();
+        h.add("Host".to_owned(), "a".to_owned()).unwrap();
+        h.add("Accept".to_owned(), "*/*".to_owned()).unwrap();
+        assert!(!h.has_duplicate_names());
+        assert!(h.duplicate_names().is_empty());
+
+        h.add("Content-Length".to_owned(), "1".to_owned()).unwrap();
+        h.add("content-length".to_owned(), "2".to_owned()).unwrap();
+        h.add("HOST".to_owned(), "b".to_owned()).unwrap();
+        h.add("Host".to_owned(), "c".to_owned()).unwrap();
+        assert!(h.has_duplicate_names());
+        assert_eq!(h.duplicate_names(), vec!["content-length", "host"]);
+    }
+
+    #[test]
+    fn test_decode_value() {
+        let h = Header::new(
+            "Subject: Re: =?utf-8?q?caf=C3=A9?= =?utf-8?b?IGF1IGxhaXQ=?= (was: =?iso-8859-1?q?th=E9?=)"
+                .as_bytes()
+                .to_vec(),
+        )
+        .unwrap();
+        assert_eq!(h.decode_value(), "Re: café au lait (was: thé)");
+    }
+
+    #[test]
+    fn test_get_combined() {
+        let mut headers = Headers::default();
+        headers
+            .add("Accept".to_owned(), "text/html ".to_owned())
+            .unwrap();
+        headers.add("Host".to_owned(), "x".to_owned()).unwrap();
+        headers
+            .add("accept".to_owned(), "application/json".to_owned())
+            .unwrap();
+        headers
+            .add(
+                "Set-Cookie".to_owned(),
+                "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT".to_owned(),
+            )
+            .unwrap();
+        headers
+            .add("Set-Cookie".to_owned(), "b=2".to_owned())
+            .unwrap();
+        assert_eq!(
+            headers.get_combined("ACCEPT"),
+            Some("text/html, application/json".to_owned())
+        );
+        assert_eq!(headers.get_combined("host"), Some("x".to_owned()));
+        assert_eq!(headers.get_combined("missing"), None);
+        assert_eq!(headers.get_combined("set-cookie"), None);
+        assert_eq!(headers.get_all("set-cookie").len(), 2);
+    }
+
+    #[test]
+    fn test_has_and_count() {
+        let mut headers = Headers::default();
+        headers.add("Accept".to_owned(), "a".to_owned()).unwrap();
+        headers.add("accept".to_owned(), "b".to_owned()).unwrap();
+        headers.add("Host".to_owned(), "x".to_owned()).unwrap();
+        assert!(headers.has("ACCEPT"));
+        assert!(!headers.has("authorization"));
+        assert_eq!(headers.count("accept"), 2);
+        assert_eq!(headers.count("host"), 1);
+        assert_eq!(headers.count("authorization"), 0);
+    }
+
+    #[test]
+    fn test_content_length() {
+        let mut h = Headers::default();
+        assert_eq!(h.content_length(), None);
+        h.add("content-length".to_owned(), " 42 ".to_owned())
+            .unwrap();
+        assert_eq!(h.content_length(), Some(Ok(42)));
+        h.add("Content-Length".to_owned(), "42".to_owned()).unwrap();
+        assert_eq!(
+            h.content_length(),
+            Some(Err(Errors::Header(
+                "Content-Length header must appear only once".to_owned()
+            )))
+        );
+
+        let mut h = Headers::default();
+        h.add("Content-Length".to_owned(), "4x".to_owned()).unwrap();
+        assert_eq!(
+            h.content_length(),
+            Some(Err(Errors::InvalidContentLength("4x".to_owned())))
+        );
+    }
+
+    #[test]
+    fn test_parse_content_length() {
+        assert_eq!(parse_content_length("42", usize::MAX), Ok(42));
+        assert_eq!(parse_content_length(" \t42 ", usize::MAX), Ok(42));
+        assert_eq!(parse_content_length("007", usize::MAX), Ok(7));
+        assert_eq!(parse_content_length("42, 42", usize::MAX), Ok(42));
+        assert_eq!(parse_content_length("42,042", usize::MAX), Ok(42));
+        for value in [
+            "",
+            "4 2",
+            "42abc",
+            "+42",
+            "-1",
+            "42, 43",
+            "42,",
+            ",",
+            "0x10",
+            "99999999999999999999999",
+        ] {
+            assert_eq!(
+                parse_content_length(value, usize::MAX),
+                Err(Errors::InvalidContentLength(value.to_owned())),
+                "{:?}",
+                value
+            );
+        }
+        assert_eq!(parse_content_length("10", 10), Ok(10));
+        assert_eq!(
+            parse_content_length("11", 10),
+            Err(Errors::ContentLengthTooLarge(11))
+        );
+    }
+
+    #[test]
+    fn test_http_date() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let date = |seconds| http_date(UNIX_EPOCH + Duration::from_secs(seconds));
+        assert_eq!(date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(date(784111777), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(date(951782400), "Tue, 29 Feb 2000 00:00:00 GMT");
+        assert_eq!(date(4102444799), "Thu, 31 Dec 2099 23:59:59 GMT");
+
+        let mut h = Headers::default();
+        h.add("Date".to_owned(), "yesterday".to_owned()).unwrap();
+        h.add("Server".to_owned(), "httplus".to_owned()).unwrap();
+        h.add_date_header();
+        assert_eq!(h.len(), 2);
+        assert_eq!(h.get_all("date").len(), 1);
+        assert!(h.get("date").unwrap().value.ends_with(" GMT"));
+        assert_eq!(h.get("date").unwrap().value.len(), 29);
+    }
+
+    #[test]
+    fn test_transfer_encodings() {
+        let mut h = Headers::default();
+        assert!(h.transfer_encodings().is_empty());
+        h.add("Transfer-Encoding".to_owned(), "GZIP, ".to_owned())
+            .unwrap();
+        h.add("Host".to_owned(), "x".to_owned()).unwrap();
+        h.add("transfer-encoding".to_owned(), "br ,Chunked".to_owned())
+            .unwrap();
+        assert_eq!(h.transfer_encodings(), vec!["gzip", "br", "chunked"]);
+    }
 }
@@ -0,0 +1,175 @@
+use std::sync::{Arc, Mutex};
+
+// the table is searched linearly, which is faster than hashing for a few dozen short names
+const DEFAULT_CAPACITY: usize = 64;
+// long names are almost certainly not going to repeat
+const MAX_NAME_LENGTH: usize = 64;
+// clients tend to spell a name the same way every time, more than a few spellings of one
+// name is a client trying to fill the table
+const MAX_SPELLINGS: usize = 4;
+
+// header names shared between every request parsed on a connection so that a name
+// like "Content-Type" is allocated once instead of once per request
+//
+// names are looked up case-insensitively, so "Host" and "host" are one entry of the table,
+// but each spelling is kept as it was received so that Header::key and dump don't depend
+// on whether a request was parsed in a Session
+#[derive(Debug, Clone)]
+pub struct Interner {
+    // the spellings of one name, e.g. ["Host", "host"]
+    names: Vec<Vec<Arc<str>>>,
+    capacity: usize,
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Interner::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl Interner {
+    // once capacity names are stored new names are no longer cached, so a client sending
+    // randomized names can't grow the table or push the common names out of it
+    pub fn with_capacity(capacity: usize) -> Self {
+        Interner {
+            names: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn intern(&mut self, name: &str) -> Arc<str> {
+        let position = self
+            .names
+            .iter()
+            .position(|spellings| spellings[0].eq_ignore_ascii_case(name));
+        if let Some(spellings) = position.map(|i| &mut self.names[i]) {
+            if let Some(interned) = spellings.iter().find(|s| s.as_ref() == name) {
+                return interned.clone();
+            }
+            let interned: Arc<str> = Arc::from(name);
+            if spellings.len() < MAX_SPELLINGS {
+                spellings.push(interned.clone());
+            }
+            return interned;
+        }
+        let interned: Arc<str> = Arc::from(name);
+        if self.names.len() < self.capacity && name.len() <= MAX_NAME_LENGTH {
+            self.names.push(vec![interned.clone()]);
+        }
+        interned
+    }
+
+    // the number of names, however many ways each of them has been spelled
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+// hands out requests that share one Interner, e.g. one Session per keep-alive connection
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    pub interner: Arc<Mutex<Interner>>,
+}
+
+impl Session {
+    pub fn new(interner: Interner) -> Self {
+        Session {
+            interner: Arc::new(Mutex::new(interner)),
+        }
+    }
+
+    pub fn request(&self) -> crate::Request {
+        crate::Request {
+            interner: Some(self.interner.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let mut interner = Interner::with_capacity(2);
+        let host = interner.intern("Host");
+        assert_eq!(host.as_ref(), "Host");
+        assert!(Arc::ptr_eq(&host, &interner.intern("Host")));
+
+        // another spelling of the same name
+        let lower = interner.intern("host");
+        assert_eq!(lower.as_ref(), "host");
+        assert!(!Arc::ptr_eq(&host, &lower));
+        assert!(Arc::ptr_eq(&lower, &interner.intern("host")));
+        assert_eq!(interner.len(), 1);
+
+        // only a few spellings of a name are kept
+        for spelling in ["HOST", "hOST", "HoST", "hoST"] {
+            interner.intern(spelling);
+        }
+        assert!(!Arc::ptr_eq(
+            &interner.intern("hoST"),
+            &interner.intern("hoST")
+        ));
+        assert!(!Arc::ptr_eq(&host, &interner.intern("Hosts")));
+        assert_eq!(interner.len(), 2);
+
+        // full, so new names are still returned but not kept
+        let accept = interner.intern("Accept");
+        assert_eq!(accept.as_ref(), "Accept");
+        assert!(!Arc::ptr_eq(&accept, &interner.intern("Accept")));
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn test_session_shares_names() {
+        let session = Session::default();
+        let mut first = session.request();
+        let mut second = session.request();
+        let raw = b"GET / HTTP/1.1\r\nHost: x\r\nAccept: */*\r\n\r\n";
+        assert_eq!(first.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(
+            second.update_raw(&mut b"GET / HTTP/1.1\r\nHost: y\r\nX-Other: z\r\n\r\n".to_vec()),
+            Ok(())
+        );
+
+        let first_key = &first.headers.values[0].key;
+        let second_key = &second.headers.values[0].key;
+        assert_eq!(second.headers.values[0].key(), "Host");
+        assert!(Arc::ptr_eq(first_key, second_key));
+        assert!(!Arc::ptr_eq(
+            &first.headers.values[1].key,
+            &second.headers.values[1].key
+        ));
+
+        // requests outside of a session don't share anything
+        let mut third = crate::Request::default();
+        assert_eq!(third.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(!Arc::ptr_eq(first_key, &third.headers.values[0].key));
+    }
+
+    #[test]
+    fn test_session_keeps_spelling() {
+        let raw = b"POST / HTTP/1.1\r\nHost: x\r\nhost: y\r\ncontent-TYPE: a\r\nContent-Length: 2\r\n\r\nok";
+        let session = Session::default();
+        for _ in 0..2 {
+            let mut interned = session.request();
+            assert_eq!(interned.update_raw(&mut raw.to_vec()), Ok(()));
+            let mut plain = crate::Request::default();
+            assert_eq!(plain.update_raw(&mut raw.to_vec()), Ok(()));
+
+            assert_eq!(interned.dump(), plain.dump());
+            assert_eq!(interned.dump(), raw.to_vec());
+            assert!(interned.headers.keys().eq(plain.headers.keys()));
+            assert_eq!(
+                interned.headers.values[1].to_string(),
+                plain.headers.values[1].to_string()
+            );
+        }
+    }
+}
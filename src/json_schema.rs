@@ -0,0 +1,108 @@
+use crate::errors::Errors;
+use serde_json::Value;
+
+/*
+    https://json-schema.org/draft/2020-12/json-schema-validation#section-7.2.1
+
+    The value of this keyword is called a format attribute. It MUST be a string.
+    A format attribute can generally only validate a given set of instance types.
+
+    Formats are only annotations by default, they are checked here since schemars emits
+    them for e.g. #[schemars(email)] and a body that ignores them is not valid for T.
+*/
+pub fn validate(schema: &Value, value: &Value) -> Result<(), Errors> {
+    let validator = jsonschema::options()
+        .should_validate_formats(true)
+        .build(schema)
+        .map_err(|e| fail(&e.instance_path().to_string(), e.to_string()))?;
+    validator
+        .validate(value)
+        .map_err(|e| fail(&e.instance_path().to_string(), e.to_string()))
+}
+
+// path is a JSON pointer to the invalid value, https://datatracker.ietf.org/doc/html/rfc6901
+fn fail(path: &str, message: String) -> Errors {
+    let path = if path.is_empty() { "/" } else { path };
+    Errors::JsonSchema(format!("{}: {}", path, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // the message itself comes from jsonschema, only where it points is checked
+    fn failed_at(result: Result<(), Errors>) -> Option<String> {
+        match result {
+            Err(Errors::JsonSchema(message)) => {
+                Some(message.split(": ").next().unwrap_or_default().to_owned())
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 1, "pattern": "^[a-z]+$" },
+                "age": { "type": "integer", "minimum": 0, "maximum": 150, "multipleOf": 2 },
+                "mail": { "type": "string", "format": "email" },
+                "tags": { "type": "array", "items": { "$ref": "#/$defs/Tag" }, "uniqueItems": true },
+                "pair": { "type": "array", "prefixItems": [{ "type": "string" }, { "type": "integer" }] }
+            },
+            "required": ["name"],
+            "additionalProperties": false,
+            "$defs": { "Tag": { "type": "string", "enum": ["a", "b/c"] } }
+        });
+        let cases = [
+            (
+                json!({"name": "x", "age": 4, "mail": "x@example.com", "tags": ["a", "b/c"], "pair": ["a", 1]}),
+                None,
+            ),
+            (json!({"name": "x", "age": null}), Some("/age")),
+            (json!({"age": 4}), Some("/")),
+            (json!({"name": ""}), Some("/name")),
+            (json!({"name": "NOT LOWER 123"}), Some("/name")),
+            (json!({"name": "x", "age": 152}), Some("/age")),
+            (json!({"name": "x", "age": 3}), Some("/age")),
+            (json!({"name": "x", "mail": "nope"}), Some("/mail")),
+            (json!({"name": "x", "tags": ["b/c", "d"]}), Some("/tags/1")),
+            (json!({"name": "x", "tags": ["a", "a"]}), Some("/tags")),
+            (json!({"name": "x", "pair": ["a", "b"]}), Some("/pair/1")),
+            (json!({"name": "x", "a/b": 1}), Some("/")),
+            (json!([]), Some("/")),
+        ];
+        for (value, path) in cases {
+            assert_eq!(
+                failed_at(validate(&schema, &value)).as_deref(),
+                path,
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_combinators() {
+        let schema = json!({
+            "oneOf": [
+                { "type": "string", "const": "none" },
+                { "type": "object", "required": ["some"] }
+            ]
+        });
+        assert_eq!(validate(&schema, &json!("none")), Ok(()));
+        assert_eq!(validate(&schema, &json!({"some": 1})), Ok(()));
+        assert!(validate(&schema, &json!("other")).is_err());
+        let schema = json!({ "type": ["number", "null"], "exclusiveMinimum": 0 });
+        assert_eq!(validate(&schema, &json!(null)), Ok(()));
+        assert_eq!(validate(&schema, &json!(0.5)), Ok(()));
+        assert!(validate(&schema, &json!(0)).is_err());
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert!(validate(&json!({ "type": 1 }), &json!(1)).is_err());
+    }
+}
@@ -1,7 +1,28 @@
-mod errors;
-mod headers;
+pub mod accept;
+pub mod chunked;
+pub mod connection;
+pub mod cookies;
+pub mod encoded_word;
+pub mod errors;
+pub mod framer;
+pub mod headers;
+pub mod intern;
+#[cfg(feature = "schemars")]
+pub mod json_schema;
+pub mod method;
+pub mod read;
+pub mod relay;
+pub mod target;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod util;
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HeadersEnd {
     #[default]
     Unset,
@@ -10,6 +31,7 @@ pub enum HeadersEnd {
 }
 
 #[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ContentLength {
     #[default]
     Unset,
@@ -17,6 +39,7 @@ pub enum ContentLength {
 }
 
 #[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Chunked {
     #[default]
     Unset,
@@ -24,7 +47,94 @@ pub enum Chunked {
     Complete,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stage {
+    RequestLine,
+    Headers,
+    Body,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseState {
+    AwaitingHeaders,
+    // expected is None for chunked bodies, received counts decoded bytes for those
+    AwaitingBody {
+        received: usize,
+        expected: Option<usize>,
+    },
+    // the head asked for "Expect: 100-continue" and no body bytes have arrived yet, the
+    // client may be waiting for a 100 (Continue) before it sends the body
+    AwaitingContinue {
+        expected: Option<usize>,
+    },
+    Complete,
+    // update_raw returned an error, the error itself is kept in Request::error
+    Failed,
+}
+
+// how far along a request is, so that read timeouts can differ per stage
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseProgress {
+    pub stage: Stage,
+    pub bytes_in_stage: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestHead {
+    pub request_line: String,
+    pub headers: headers::Headers,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseOptions {
+    // accept a bare LF as a line terminator in the header section
+    pub lenient_line_endings: bool,
+    // reject requests that are well formed but use values the RFCs do not define
+    pub strict: bool,
+    // keep the head only: body bytes are counted and dropped instead of being stored in raw
+    pub headers_only: bool,
+    // reject a Content-Length above this, e.g. to respond 413 before any of the body is read
+    pub max_content_length: Option<usize>,
+    // where Request::size_class draws its lines
+    pub size_class_thresholds: SizeClassThresholds,
+}
+
+// a rough body size for routing and rate limiting, see Request::size_class
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SizeClass {
+    Empty,
+    Small,
+    Medium,
+    Large,
+    ExtraLarge,
+}
+
+// the largest body size, inclusive, of each class, anything bigger than large is ExtraLarge
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizeClassThresholds {
+    pub small: usize,
+    pub medium: usize,
+    pub large: usize,
+}
+
+impl Default for SizeClassThresholds {
+    fn default() -> Self {
+        SizeClassThresholds {
+            small: 1024,
+            medium: 64 * 1024,
+            large: 1024 * 1024,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "RequestFields", into = "RequestFields")
+)]
 pub struct Request {
     pub request_line: String,
     pub headers: headers::Headers,
@@ -32,8 +142,106 @@ pub struct Request {
     pub raw: Vec<u8>,
     pub content_length: ContentLength,
     pub is_chunked: Chunked,
+    pub chunk_decoder: chunked::ChunkDecoder,
+    pub options: ParseOptions,
+    // header names are shared with the other requests of an intern::Session when set
+    pub interner: Option<Arc<Mutex<intern::Interner>>>,
+    // the error returned by update_raw, once set the request will not parse any further
+    // NOTE: errors are not serialized
+    pub error: Option<errors::Errors>,
+    // body bytes (including chunk framing) dropped because of ParseOptions::headers_only
+    // or moved out of raw by take_body
+    pub discarded_body_bytes: usize,
+    // set by take_body, the request can't be fed any more data after that
+    pub body_taken: bool,
+    // see on_body
+    pub body_callback: Option<BodyCallback>,
+    // the CRs added in front of bare LFs because of ParseOptions::lenient_line_endings,
+    // raw holds this many more bytes than were received
+    pub inserted_carriage_returns: usize,
+    // the empty lines received before the request line, they are not kept in raw
+    pub skipped_empty_lines: usize,
+}
+
+// what a Request is serialized as: the head and the body as it was sent, chunk framing
+// included, base64 encoded
+// deserializing feeds the rebuilt message to update_raw so every other field is derived
+// again just like it was while parsing, with the default ParseOptions
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RequestFields {
+    request_line: String,
+    headers: headers::Headers,
+    #[serde(with = "util::base64_bytes")]
+    body: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Request> for RequestFields {
+    fn from(r: Request) -> Self {
+        // until the head is complete there is nothing but the bytes received so far
+        if !matches!(r.headers_end, HeadersEnd::FoundAt(_)) {
+            return RequestFields {
+                request_line: String::new(),
+                headers: headers::Headers::default(),
+                body: r.raw,
+            };
+        }
+        RequestFields {
+            body: r.wire_body().to_vec(),
+            request_line: r.request_line,
+            headers: r.headers,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RequestFields> for Request {
+    type Error = errors::Errors;
+
+    fn try_from(fields: RequestFields) -> Result<Self, errors::Errors> {
+        let mut wire = if fields.request_line.is_empty() {
+            vec![]
+        } else {
+            dump_head(&fields.request_line, &fields.headers)
+        };
+        wire.extend_from_slice(&fields.body);
+
+        let mut r = Request::default();
+        r.update_raw(&mut wire)?;
+        Ok(r)
+    }
+}
+
+type BodyFn = dyn FnMut(&[u8]) -> std::io::Result<()> + Send;
+
+// hands the body to a closure as it arrives, see Request::on_body
+#[derive(Clone)]
+pub struct BodyCallback {
+    f: Arc<Mutex<BodyFn>>,
+    // how much of the body has been handed over so far
+    delivered: usize,
+}
+
+impl std::fmt::Debug for BodyCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "BodyCallback[delivered={}]", self.delivered)
+    }
 }
 
+// fields that are not a comma separated list, so more than one of them is ambiguous
+// https://www.rfc-editor.org/rfc/rfc7230#section-3.2.2
+const SINGLETON_HEADERS: [&str; 8] = [
+    "host",
+    "content-length",
+    "content-type",
+    "authorization",
+    "proxy-authorization",
+    "if-modified-since",
+    "if-unmodified-since",
+    "max-forwards",
+];
+
 const LINE_END: &[u8; 2] = b"\r\n";
 const HEADER_END: &[u8; 4] = b"\r\n\r\n";
 
@@ -45,389 +253,3648 @@ const HEADER_END: &[u8; 4] = b"\r\n\r\n";
                    [ message-body ]
 */
 
+/*
+    https://www.rfc-editor.org/rfc/rfc7230#section-2.6
+
+    HTTP-version  = HTTP-name "/" DIGIT "." DIGIT
+    HTTP-name     = %x48.54.54.50 ; "HTTP", case-sensitive
+*/
+fn parse_http_version(token: &str) -> Option<(u8, u8)> {
+    match token.strip_prefix("HTTP/")?.as_bytes() {
+        [major, b'.', minor] if major.is_ascii_digit() && minor.is_ascii_digit() => {
+            Some((major - b'0', minor - b'0'))
+        }
+        _ => None,
+    }
+}
+
+fn dump_head(request_line: &str, headers: &headers::Headers) -> Vec<u8> {
+    let mut dump = vec![];
+    dump.append(&mut request_line.as_bytes().to_vec());
+    dump.append(&mut LINE_END.to_vec());
+    for h in headers.values.iter() {
+        dump.append(&mut format!("{}: {}", h.key, h.value).as_bytes().to_vec());
+        dump.append(&mut LINE_END.to_vec());
+    }
+    dump.append(&mut LINE_END.to_vec());
+    dump
+}
+
 impl Request {
+    // a request that also accepts bare LF line endings in the header section
+    pub fn lenient() -> Self {
+        Request {
+            options: ParseOptions {
+                lenient_line_endings: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // a request with room for body_size bytes of raw data and header_count headers, so that
+    // update_raw does not have to grow them when the sizes are known in advance
+    pub fn with_capacity(header_count: usize, body_size: usize) -> Self {
+        Request {
+            raw: Vec::with_capacity(body_size),
+            headers: headers::Headers {
+                values: Vec::with_capacity(header_count),
+            },
+            ..Default::default()
+        }
+    }
+
+    // a request that rejects values the RFCs leave undefined, e.g. an unknown Expect,
+    // HTTP/1.1 requests without exactly one Host header and folded header lines
+    pub fn strict() -> Self {
+        Request {
+            options: ParseOptions {
+                strict: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // an upgrade request never completes, its dump is the head followed by everything received
+    // after it, which belongs to the upgraded protocol rather than to the request
     pub fn dump(&self) -> Vec<u8> {
+        if self.is_upgrade() {
+            if let HeadersEnd::FoundAt(at) = self.headers_end {
+                let mut dump = dump_head(&self.request_line, &self.headers);
+                dump.extend_from_slice(&self.raw[at + HEADER_END.len()..]);
+                return dump;
+            }
+        }
         if !self.body_complete() {
             return vec![];
         }
-        let mut dump = vec![];
-        dump.append(&mut self.request_line.as_bytes().to_vec());
-        dump.append(&mut LINE_END.to_vec());
-        dump.append(
-            &mut self
-                .headers
-                .values
-                .iter()
-                .map(|h| format!("{}: {}", h.key, h.value))
-                .collect::<Vec<String>>()
-                .join("\r\n")
-                .as_bytes()
-                .to_vec(),
-        );
-        dump.append(&mut HEADER_END.to_vec());
-        if self.body_complete() {
-            dump.append(&mut self.body());
-        }
+        let mut dump = dump_head(&self.request_line, &self.headers);
+        dump.append(&mut self.wire_body().to_vec());
         dump
     }
 
-    pub fn body(&self) -> Vec<u8> {
-        match self.headers_end {
-            HeadersEnd::FoundAt(at) => self.raw[at + HEADER_END.len()..].to_vec(),
-            _ => vec![],
+    // the same request with its body re-framed as chunks of at most chunk_size bytes,
+    // any trailers received are sent after the last chunk
+    pub fn dump_chunked(&self, chunk_size: usize) -> Result<Vec<u8>, errors::Errors> {
+        if chunk_size == 0 {
+            return Err(errors::Errors::Chunk(
+                "chunk size must be greater than zero",
+            ));
+        }
+        if !self.body_complete() {
+            return Ok(vec![]);
+        }
+
+        let mut headers = headers::Headers::default();
+        for header in self.headers.values.iter() {
+            if !header.name_is("content-length") && !header.name_is("transfer-encoding") {
+                headers.values.push(header.clone());
+            }
+        }
+        // any other codings still apply to the body, only the chunk framing is redone
+        let mut codings = self.headers.transfer_encodings();
+        codings.retain(|c| c != "chunked");
+        codings.push("chunked".to_owned());
+        headers.add("Transfer-Encoding".to_owned(), codings.join(", "))?;
+
+        let mut dump = dump_head(&self.request_line, &headers);
+        for chunk in self.body_slice().chunks(chunk_size) {
+            dump.extend_from_slice(format!("{:x}", chunk.len()).as_bytes());
+            dump.extend_from_slice(LINE_END);
+            dump.extend_from_slice(chunk);
+            dump.extend_from_slice(LINE_END);
         }
+        dump.extend_from_slice(b"0");
+        dump.extend_from_slice(LINE_END);
+        for trailer in self.trailers().values.iter() {
+            dump.extend_from_slice(format!("{}: {}", trailer.key, trailer.value).as_bytes());
+            dump.extend_from_slice(LINE_END);
+        }
+        dump.extend_from_slice(LINE_END);
+        Ok(dump)
     }
 
-    pub fn body_complete(&self) -> bool {
-        match self.headers_end {
-            HeadersEnd::Unset => false,
-            HeadersEnd::Scanning(_) => false,
-            HeadersEnd::FoundAt(at) => {
-                match self.is_chunked {
-                    Chunked::Unset => false,
-                    Chunked::Processing => false,
-                    Chunked::Complete => true,
-                };
-                match self.content_length {
-                    ContentLength::Unset => true,
-                    ContentLength::Value(content_length) => {
-                        self.raw[at + HEADER_END.len()..].len() == content_length
-                    }
+    // split a request into its head and (framed) body, see from_parts for the reverse
+    pub fn into_parts(self) -> (RequestHead, Vec<u8>) {
+        let body = self.wire_body().to_vec();
+        (
+            RequestHead {
+                request_line: self.request_line,
+                headers: self.headers,
+            },
+            body,
+        )
+    }
+
+    // reassemble a request by feeding the wire format back through the parser so that
+    // all of the framing state (content-length, chunked) is derived exactly as it would be
+    // for a request read off of the network
+    // NOTE: a chunked body must be complete, including the last chunk and the trailers, and
+    // end there
+    pub fn from_parts(head: RequestHead, mut body: Vec<u8>) -> Result<Request, errors::Errors> {
+        let body_len = body.len();
+        let mut raw = dump_head(&head.request_line, &head.headers);
+        raw.append(&mut body);
+
+        let mut request = Request::default();
+        request.update_raw(&mut raw)?;
+
+        match request.content_length {
+            ContentLength::Value(content_length) => {
+                if content_length != body_len {
+                    return Err(errors::Errors::Header(
+                        "Content-Length does not match the length of the body".to_owned(),
+                    ));
+                }
+            }
+            ContentLength::Unset => {
+                if request.is_chunked == Chunked::Unset && body_len != 0 {
+                    return Err(errors::Errors::Header(
+                        "a body requires either a Content-Length or a chunked Transfer-Encoding"
+                            .to_owned(),
+                    ));
+                }
+                if request.is_chunked != Chunked::Unset && !request.body_complete() {
+                    return Err(errors::Errors::Invalid(errors::ValidationIssue::Incomplete));
+                }
+                if request.excess_bytes() != 0 {
+                    return Err(errors::Errors::Chunk("data after the last chunk"));
                 }
             }
         }
+        Ok(request)
     }
 
-    pub fn update_raw(&mut self, data: &mut Vec<u8>) -> Result<(), errors::Errors> {
-        self.raw.append(data);
+    // from_parts with value serialized as the body, any Content-Type, Content-Length or
+    // Transfer-Encoding in head is replaced
+    #[cfg(feature = "serde_json")]
+    pub fn from_json<T: serde::Serialize>(
+        mut head: RequestHead,
+        value: &T,
+    ) -> Result<Request, errors::Errors> {
+        let body = serde_json::to_vec(value).map_err(|e| errors::Errors::Json(e.to_string()))?;
+        head.headers.values.retain(|h| {
+            !h.name_is("content-type")
+                && !h.name_is("content-length")
+                && !h.name_is("transfer-encoding")
+        });
+        head.headers
+            .add("Content-Type".to_owned(), "application/json".to_owned())?;
+        head.headers
+            .add("Content-Length".to_owned(), body.len().to_string())?;
+        Request::from_parts(head, body)
+    }
 
+    // the body parsed as a T, which it must also be valid for according to the JSON Schema
+    // that schemars generates for T, e.g. a #[schemars(range(max = 150))] is enforced
+    #[cfg(feature = "schemars")]
+    pub fn validate_json_body<T: serde::de::DeserializeOwned + schemars::JsonSchema>(
+        &self,
+    ) -> Result<T, errors::Errors> {
+        let json = |e: serde_json::Error| errors::Errors::Json(e.to_string());
+        let value: serde_json::Value = serde_json::from_slice(&self.body()).map_err(json)?;
+        json_schema::validate(schemars::schema_for!(T).as_value(), &value)?;
+        serde_json::from_value(value).map_err(json)
+    }
+
+    // the request-target is the second token of the request line:
+    // https://www.rfc-editor.org/rfc/rfc7230#section-3.1.1
+    fn target(&self) -> Option<&str> {
+        self.request_line.split(' ').nth(1)
+    }
+
+    // the method token as it was sent, once the head has been received
+    pub fn method(&self) -> Option<&str> {
         match self.headers_end {
-            HeadersEnd::Unset => self.attempt_header_parsing(0),
-            HeadersEnd::Scanning(index) => self.attempt_header_parsing(index),
-            HeadersEnd::FoundAt(_) => Ok(()),
+            HeadersEnd::FoundAt(_) => self.request_line.split(' ').next(),
+            _ => None,
         }
     }
 
-    fn attempt_header_parsing(&mut self, mut at: usize) -> Result<(), errors::Errors> {
-        while at < self.raw.len() {
-            if self.raw[at..].starts_with(HEADER_END) {
-                self.headers_end = HeadersEnd::FoundAt(at);
-                break;
-            }
-            at += 1;
+    // the still percent-encoded path of the request-target, without the query
+    pub fn path(&self) -> Option<&str> {
+        match self.headers_end {
+            HeadersEnd::FoundAt(_) => Some(target::split(self.target()?).path),
+            _ => None,
         }
+    }
 
-        if let HeadersEnd::FoundAt(_) = self.headers_end {
-            self.parse_and_fill_headers()?;
-        } else {
-            // raw data might come in that splits the HEADER_END in two:
-            // EG:
-            //  previous append to raw: "\r"
-            //  next append to raw: "\n\r\n"
-            //
-            // as a result, backup enough to find a complete HEADER_END
-            self.headers_end = HeadersEnd::Scanning(at - HEADER_END.len());
-        }
-        Ok(())
+    // everything a router needs: the method and the decoded, normalized path without the query
+    pub fn route(&self) -> Result<(method::Method, String), errors::Errors> {
+        let method = match self.request_line.split(' ').next() {
+            Some(token) => method::Method::parse(token)?,
+            None => return Err(errors::Errors::RequestLine("missing method")),
+        };
+        Ok((method, self.normalized_path()?))
     }
 
-    fn parse_and_fill_headers(&mut self) -> Result<(), errors::Errors> {
-        if let HeadersEnd::FoundAt(end) = self.headers_end {
-            let header_chunk = self.raw[0..end].to_vec();
+    fn http_version(&self) -> Option<(u8, u8)> {
+        parse_http_version(self.request_line.split(' ').nth(2)?)
+    }
 
-            let mut newline_indices = header_chunk
-                .windows(2)
-                .enumerate()
-                .filter(|(_, w)| w == LINE_END)
-                .map(|(i, _)| i)
-                .collect::<Vec<_>>();
-            newline_indices.push(header_chunk.len());
+    // e.g. 1 for HTTP/1.0 and HTTP/1.1, once the request line has been received
+    pub fn version_major(&self) -> Option<u8> {
+        self.http_version().map(|(major, _)| major)
+    }
 
-            let mut newline = newline_indices.iter();
-            let mut at = newline.next().unwrap();
+    pub fn version_minor(&self) -> Option<u8> {
+        self.http_version().map(|(_, minor)| minor)
+    }
 
-            match String::from_utf8(header_chunk[0..*at].to_owned()) {
-                // TODO: check that the first line of the HTTP request is valid
-                Ok(s) => self.request_line = s,
-                Err(e) => return Err(errors::Errors::Parse(e)),
-            };
+    /*
+        https://www.rfc-editor.org/rfc/rfc7230#section-6.3
 
-            loop {
-                let sindex = at + LINE_END.len();
-                let mut eindex = match newline.next() {
-                    Some(eindex) => eindex,
-                    None => break,
-                };
+        If the "close" connection option is present, the connection will not persist
+        after the current response; else, if the received protocol is HTTP/1.1 (or later),
+        the connection will persist after the current response; else, if the received
+        protocol is HTTP/1.0, the "keep-alive" connection option is present ... the
+        connection will persist after the current response; otherwise, the connection
+        will close after the current response.
+    */
+    pub fn keep_alive(&self) -> bool {
+        let options = self
+            .headers
+            .get_all("connection")
+            .iter()
+            .flat_map(|h| h.value_list())
+            .collect::<Vec<String>>();
+        let has_option = |option: &str| options.iter().any(|o| o.eq_ignore_ascii_case(option));
 
-                let mut skip_fold_spaces: Vec<usize> = vec![sindex, *eindex];
+        if has_option("close") {
+            return false;
+        }
+        match self.http_version() {
+            Some((major, minor)) if (major, minor) >= (1, 1) => true,
+            Some((1, 0)) => has_option("keep-alive"),
+            _ => false,
+        }
+    }
 
-                loop {
-                    if eindex == &header_chunk.len() {
-                        break;
-                    }
+    pub fn normalized_path(&self) -> Result<String, errors::Errors> {
+        self.normalized_path_with(false)
+    }
 
-                    /*
-                      https://www.rfc-editor.org/rfc/rfc7230
+    // percent-decode the path and remove dot-segments so that routing and static file
+    // lookups can never be tricked into escaping the root (eg: "/a/../../etc")
+    pub fn normalized_path_with(&self, collapse_slashes: bool) -> Result<String, errors::Errors> {
+        let decoded = self.decoded_path()?;
+        Ok(target::remove_dot_segments(&decoded, collapse_slashes))
+    }
 
-                      A proxy or gateway that receives an obs-fold in a response message
-                      that is not within a message/http container MUST either discard the
-                      message and replace it with a 502 (Bad Gateway) response, preferably
-                      with a representation explaining that unacceptable line folding was
-                      received, or replace each received obs-fold with one or more SP
-                      octets prior to interpreting the field value or forwarding the
-                      message downstream.
+    fn split_target(&self) -> Result<target::Target<'_>, errors::Errors> {
+        match self.target() {
+            Some(target) => Ok(target::split(target)),
+            None => Err(errors::Errors::RequestLine("missing request-target")),
+        }
+    }
 
-                      https://www.ietf.org/rfc/rfc2616.txt
+    // the percent-decoded path of the request-target, a "+" is left as is
+    pub fn decoded_path(&self) -> Result<String, errors::Errors> {
+        let path = self.split_target()?.path;
+        Ok(String::from_utf8(target::percent_decode(path, false)?)?)
+    }
 
-                      All linear white space, including folding, has the same semantics as SP. A
-                      recipient MAY replace any linear white space with a single SP before
-                      interpreting the field value or forwarding the message downstream.
+    // the query of the request-target as it was sent, without the "?"
+    pub fn query_string(&self) -> Option<&str> {
+        target::split(self.target()?).query
+    }
 
-                      LWS            = [CRLF] 1*( SP | HT )
+    // the decoded query pairs in the order they were sent, a "+" is decoded as a space
+    pub fn query(&self) -> Result<Vec<(String, String)>, errors::Errors> {
+        match self.split_target()?.query {
+            Some(query) => target::parse_query(query),
+            None => Ok(vec![]),
+        }
+    }
 
-                      In other words, one or more spaces or tabs must be replaced with a single space.
-                    */
+    // the same pairs as query, but a pair that can't be decoded is skipped instead of
+    // failing the whole query
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        let query = match self.split_target() {
+            Ok(target) => target.query.unwrap_or_default(),
+            Err(_) => "",
+        };
+        query
+            .split('&')
+            .flat_map(|pair| target::parse_query(pair).unwrap_or_default())
+            .collect()
+    }
 
-                    // evaluate the first byte(s) in the next line
-                    // to determine if we are dealing with a "line folded" header
-                    let mut offset = 0;
-                    let mut is_line_fold = false;
+    // the same as query_pairs, named to go with decoded_path
+    pub fn decoded_query_pairs(&self) -> Vec<(String, String)> {
+        self.query_pairs()
+    }
 
-                    let mut next_non_empty_char = header_chunk[eindex + LINE_END.len() + offset];
-                    while next_non_empty_char == b'\t' || next_non_empty_char == b' ' {
-                        offset += 1;
-                        next_non_empty_char = header_chunk[eindex + LINE_END.len() + offset];
-                        is_line_fold = true;
-                    }
+    // the value of the first query pair named name
+    pub fn query_get(&self, name: &str) -> Result<Option<String>, errors::Errors> {
+        Ok(self
+            .query()?
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value))
+    }
 
-                    if is_line_fold {
-                        let sindex = eindex + LINE_END.len() + offset;
-                        eindex = match newline.next() {
-                            Some(eindex) => eindex,
-                            None => break,
-                        };
-                        skip_fold_spaces.push(sindex);
-                        skip_fold_spaces.push(*eindex);
-                    } else {
-                        break;
-                    }
-                }
-                at = eindex;
+    // the host (and port) of an absolute-form or authority-form request-target
+    pub fn authority(&self) -> Option<&str> {
+        target::split(self.target()?).authority
+    }
 
-                // reduce spaces and tabs in "line folded" headers to a single space
-                let mut header: Vec<u8> = vec![];
-                for i in 0..skip_fold_spaces.len() {
-                    if i % 2 == 1 {
-                        continue;
-                    }
-                    let mut chunk =
-                        header_chunk[skip_fold_spaces[i]..skip_fold_spaces[i + 1]].to_owned();
-                    header.append(&mut chunk);
-                }
+    // the name and port from the Host header, see Request::strict for rejecting a missing
+    // or repeated Host header
+    pub fn host(&self) -> Option<(String, Option<u16>)> {
+        let host = self.headers.get("host")?;
+        let (name, port) = target::split_host(host.trimmed_value())?;
+        Some((name.to_owned(), port))
+    }
 
-                let header = headers::Header::new(header)?;
-                let key = header.key.to_lowercase();
+    // every coding listed in the Accept-Encoding header(s), in the order it was sent
+    pub fn accept_encoding(&self) -> Vec<(String, f32)> {
+        self.headers
+            .get_all("accept-encoding")
+            .iter()
+            .flat_map(|h| accept::parse_weighted(&h.value))
+            .collect()
+    }
 
-                if key == "content-length" {
-                    match self.content_length {
-                        ContentLength::Value(_) => {
-                            return Err(errors::Errors::Header(
-                                "Content-Length header must appear only once",
-                            ))
-                        }
-                        ContentLength::Unset => {
-                            self.content_length = match header.value.trim().parse::<usize>() {
-                                Ok(i) => ContentLength::Value(i),
-                                Err(e) => return Err(errors::Errors::ContentLength(e)),
-                            };
-                        }
-                    }
-                }
+    /*
+        https://www.rfc-editor.org/rfc/rfc7231#section-5.3.5
 
-                // check for chunked state: Transfer-Encoding: gzip, chunked
-                if key == "transfer-encoding" {
-                    if header.value.contains("chunked") && !header.value.ends_with("chunked") {
-                        return Err(errors::Errors::Header(
-                            "chunked must appear at the very end of the Transfer-Encoding header value",
-                        ));
-                    }
-                    if header.value.ends_with("chunked") {
-                        match self.is_chunked {
-                            Chunked::Processing => {
-                                return Err(errors::Errors::Header(
-                                    "Transfer-Encoding must appear only once",
-                                ))
-                            }
-                            Chunked::Complete => {
-                                return Err(errors::Errors::Header(
-                                    "Unexpected chunked status: Complete",
-                                ))
-                            }
-                            Chunked::Unset => {
-                                self.is_chunked = Chunked::Processing;
-                            }
-                        }
-                    }
-                }
+        Accept-Language = 1#( language-range [ weight ] )
+        language-range  = <language-range, see [RFC4647], Section 2.1>
 
-                let content_length_set = match self.content_length {
-                    ContentLength::Unset => false,
-                    _ => true,
-                };
-                let is_chunked_set = match self.is_chunked {
-                    Chunked::Unset => false,
-                    _ => true,
-                };
-                if content_length_set && is_chunked_set {
-                    return Err(errors::Errors::Header(
-                        "Transfer-Encoding and Content-Length headers are mutually exclusive",
-                    ));
-                }
+        language-range   = (1*8ALPHA *("-" 1*8alphanum)) / "*"
+    */
+    // every language range with its quality, highest quality first (ties keep the order sent)
+    // a quality of 0 means "not acceptable", those ranges are kept at the end
+    pub fn accept_language(&self) -> Vec<(String, f32)> {
+        let mut languages = self
+            .headers
+            .get_all("accept-language")
+            .iter()
+            .flat_map(|h| accept::parse_weighted(&h.value))
+            .collect::<Vec<(String, f32)>>();
+        languages.sort_by_key(|(_, quality)| Reverse(accept::OrderedF32(*quality)));
+        languages
+    }
 
-                self.headers.values.push(header.clone());
+    // Accept-Encoding codings grouped by quality, highest quality first
+    // codings with a quality of 0 are "not acceptable" and are left out
+    pub fn accept_encoding_weighted(&self) -> BTreeMap<Reverse<accept::OrderedF32>, Vec<String>> {
+        let mut weighted: BTreeMap<Reverse<accept::OrderedF32>, Vec<String>> = BTreeMap::new();
+        for (coding, quality) in self.accept_encoding() {
+            if quality > 0.0 {
+                weighted
+                    .entry(Reverse(accept::OrderedF32(quality)))
+                    .or_default()
+                    .push(coding);
             }
-        } else {
-            return Err(errors::Errors::CannotFillHeaders);
         }
-        Ok(())
+        weighted
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     /*
-    #[test]
-    fn test_chunked() {
-        // TODO: https://stackoverflow.com/questions/5590791/http-chunked-encoding-need-an-example-of-trailer-mentioned-in-spec
-        let mut r = Request::default();
-        let res = r.update_raw(
-            &mut "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n7\r\npedia i\r\nB\r\nn \r\nchunks.\r\n0\r\n\r\n"
+        https://www.rfc-editor.org/rfc/rfc7230#section-6.7
 
-                .as_bytes()
-                .to_vec(),
-        );
+        A sender of Upgrade MUST also send an "Upgrade" connection option in
+        the Connection header field (Section 6.1) to inform intermediaries
+        not to forward this field.
+    */
+    pub fn is_upgrade(&self) -> bool {
+        let upgrade = self
+            .headers
+            .get_all("upgrade")
+            .iter()
+            .any(|h| !h.value_list().is_empty());
+        upgrade
+            && self
+                .headers
+                .get_all("connection")
+                .iter()
+                .flat_map(|h| h.value_list())
+                .any(|option| option.eq_ignore_ascii_case("upgrade"))
+    }
+
+    // the Transfer-Encoding codings, empty until the headers have been parsed
+    pub fn effective_transfer_encodings(&self) -> Vec<String> {
+        match self.headers_end {
+            HeadersEnd::FoundAt(_) => self.headers.transfer_encodings(),
+            _ => vec![],
+        }
+    }
+
+    pub fn is_multipart(&self) -> bool {
+        self.headers.get("content-type").is_some_and(|h| {
+            h.trimmed_value()
+                .get(..10)
+                .is_some_and(|t| t.eq_ignore_ascii_case("multipart/"))
+        })
+    }
+
+    /*
+        https://datatracker.ietf.org/doc/html/rfc2046#section-5.1.1
+
+        boundary := 0*69<bchars> bcharsnospace
+
+        bchars := bcharsnospace / " "
+
+        bcharsnospace := DIGIT / ALPHA / "'" / "(" / ")" /
+                         "+" / "_" / "," / "-" / "." /
+                         "/" / ":" / "=" / "?"
+    */
+    // None when a multipart request has no boundary, since its body can't be split
+    pub fn boundary(&self) -> Option<&str> {
+        if !self.is_multipart() {
+            return None;
+        }
+        let value = self.headers.get("content-type")?.trimmed_value();
+        value.split(';').skip(1).find_map(|param| {
+            let (name, boundary) = param.split_once('=')?;
+            if !name.trim().eq_ignore_ascii_case("boundary") {
+                return None;
+            }
+            let boundary = boundary.trim();
+            let boundary = boundary
+                .strip_prefix('"')
+                .and_then(|b| b.strip_suffix('"'))
+                .unwrap_or(boundary);
+            Some(boundary).filter(|b| !b.is_empty())
+        })
+    }
+
+    // https://www.w3.org/TR/upgrade-insecure-requests/#preference
+    pub fn upgrade_insecure_requests(&self) -> bool {
+        self.headers
+            .get("upgrade-insecure-requests")
+            .map(|h| h.trimmed_value() == "1")
+            .unwrap_or(false)
+    }
+
+    // the DNSSEC header some DNS-over-HTTPS implementations add: "ok" or "insecure"
+    // any other value is treated the same as a missing header
+    pub fn dnssec_status(&self) -> Option<bool> {
+        let value = self.headers.get("dnssec")?.trimmed_value();
+        if value.eq_ignore_ascii_case("ok") {
+            Some(true)
+        } else if value.eq_ignore_ascii_case("insecure") {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    // compare a secret (an api key, a bearer token) with the value of the named header
+    // without the time taken depending on how much of the secret was guessed correctly
+    // NOTE: the time still depends on the length of the value that was sent
+    pub fn header_eq_constant_time(&self, name: &str, expected: &[u8]) -> bool {
+        let value = match self.headers.get(name) {
+            Some(header) => header.trimmed_value().as_bytes(),
+            None => return false,
+        };
+        let mut difference = value.len() ^ expected.len();
+        for (i, byte) in value.iter().enumerate() {
+            difference |= (byte ^ expected.get(i).copied().unwrap_or(0)) as usize;
+        }
+        std::hint::black_box(difference) == 0
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc7231#section-5.1.1
+    pub fn expect_header_value(&self) -> Option<&str> {
+        self.headers.get("expect").map(|h| h.trimmed_value())
+    }
+
+    pub fn is_expect_header_present(&self) -> bool {
+        self.expect_header_value().is_some()
+    }
+
+    // the client is waiting for a 100 (Continue) before it sends the body
+    pub fn expect_continue(&self) -> bool {
+        self.expect_header_value()
+            .is_some_and(|v| v.eq_ignore_ascii_case("100-continue"))
+    }
+
+    // the whole head has been received, so e.g. a 100 (Continue) can be sent before the body
+    pub fn has_full_headers(&self) -> bool {
+        matches!(self.headers_end, HeadersEnd::FoundAt(_))
+    }
+
+    // the same as has_full_headers, to pair with body_complete
+    pub fn headers_complete(&self) -> bool {
+        self.has_full_headers()
+    }
+
+    // the decoded body: chunk framing is removed from chunked bodies
+    pub fn body(&self) -> Vec<u8> {
+        self.body_slice().to_vec()
+    }
+
+    // move the body out of a complete request instead of copying it, raw keeps the head and
+    // the excess_bytes received after the body, which are then the last bytes of raw
+    // NOTE: afterwards body and dump have no body to return and update_raw fails
+    pub fn take_body(&mut self) -> Option<Vec<u8>> {
+        if self.body_taken || self.options.headers_only || !self.body_complete() {
+            return None;
+        }
+        let head_len = match self.headers_end {
+            HeadersEnd::FoundAt(at) => at + HEADER_END.len(),
+            _ => return None,
+        };
+        let consumed = self.consumed_len();
+        let mut body = self.raw.split_off(head_len);
+        let excess = body.split_off(consumed - head_len);
+        self.raw.extend_from_slice(&excess);
+        self.discarded_body_bytes = body.len();
+        self.body_taken = true;
+        match (&self.is_chunked, &self.content_length) {
+            (Chunked::Unset, _) => Some(body),
+            _ => Some(std::mem::take(&mut self.chunk_decoder.body)),
+        }
+    }
+
+    // every body byte is passed to f as soon as it arrives (decoded for a chunked body), set
+    // ParseOptions::headers_only as well to stream a large upload without keeping it in raw
+    pub fn on_body(&mut self, mut f: impl FnMut(&[u8]) + Send + 'static) {
+        self.set_body_callback(move |bytes| {
+            f(bytes);
+            Ok(())
+        });
+    }
+
+    // on_body into a writer, e.g. a file, a failed write fails update_raw with Errors::Io
+    pub fn body_writer(&mut self, mut writer: impl std::io::Write + Send + 'static) {
+        self.set_body_callback(move |bytes| writer.write_all(bytes));
+    }
+
+    fn set_body_callback(&mut self, f: impl FnMut(&[u8]) -> std::io::Result<()> + Send + 'static) {
+        self.body_callback = Some(BodyCallback {
+            f: Arc::new(Mutex::new(f)),
+            delivered: 0,
+        });
+    }
+
+    fn stream_body(&self, bytes: &[u8]) -> Result<(), errors::Errors> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        if let Some(callback) = &self.body_callback {
+            if let Ok(mut f) = callback.f.lock() {
+                f(bytes).map_err(|e| errors::Errors::Io(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    // the part of a body kept in raw that has not been handed to the callback yet
+    fn stream_retained_body(&mut self) -> Result<(), errors::Errors> {
+        let delivered = match &self.body_callback {
+            Some(callback) => callback.delivered,
+            None => return Ok(()),
+        };
+        let body = self.body_slice();
+        let len = body.len();
+        self.stream_body(&body[delivered.min(len)..])?;
+        if let Some(callback) = self.body_callback.as_mut() {
+            callback.delivered = len;
+        }
+        Ok(())
+    }
+
+    // the lines of a complete line-delimited body (e.g. application/x-ndjson), a CRLF ends a
+    // line the same as an LF does, and None is also returned when the body is not utf8
+    pub fn body_lines(&self) -> Option<impl Iterator<Item = &str>> {
+        if !self.body_complete() {
+            return None;
+        }
+        std::str::from_utf8(self.body_slice())
+            .ok()
+            .map(|body| body.lines())
+    }
+
+    // borrow the body instead of copying it, for Content-Length bodies this points into raw
+    // NOTE: the slice is only valid until the next call to update_raw
+    pub fn body_slice(&self) -> &[u8] {
+        if self.options.headers_only || self.body_taken {
+            return &[];
+        }
+        if self.is_chunked != Chunked::Unset {
+            return &self.chunk_decoder.body;
+        }
+        if self.content_length == ContentLength::Unset {
+            return &[];
+        }
+        match self.headers_end {
+            HeadersEnd::FoundAt(at) => &self.raw[at + HEADER_END.len()..self.consumed_len()],
+            _ => &[],
+        }
+    }
+
+    // the body exactly as it was received, including any chunk framing and trailers
+    fn wire_body(&self) -> &[u8] {
+        if self.options.headers_only || self.body_taken {
+            return &[];
+        }
+        match self.headers_end {
+            HeadersEnd::FoundAt(at) => &self.raw[at + HEADER_END.len()..self.consumed_len()],
+            _ => &[],
+        }
+    }
+
+    // trailer fields sent after the last chunk of a chunked body
+    pub fn trailers(&self) -> &headers::Headers {
+        &self.chunk_decoder.trailers
+    }
+
+    /*
+        https://www.rfc-editor.org/rfc/rfc7230#section-4.4
+
+        When a message includes a message body encoded with the chunked
+        transfer coding and the sender desires to send metadata in the form
+        of trailer fields at the end of the message, the sender SHOULD
+        generate a Trailer header field before the message body to indicate
+        which fields will be present in the trailers.
+
+        Trailer = 1#field-name
+    */
+    // the lowercased names listed by every Trailer header, which may differ from what is
+    // actually sent in trailers()
+    pub fn declared_trailer_names(&self) -> Vec<String> {
+        self.headers
+            .get_all("trailer")
+            .iter()
+            .flat_map(|h| h.value_list())
+            .map(|name| name.to_ascii_lowercase())
+            .collect()
+    }
+
+    // the extensions of every chunk received so far, including the last (zero sized) chunk
+    #[cfg(feature = "extensions")]
+    pub fn chunk_extensions(&self) -> &[Vec<chunked::ChunkExt>] {
+        &self.chunk_decoder.extensions
+    }
+
+    // checks that go beyond what is needed to frame the request, every issue found is returned
+    pub fn validate_request(&self) -> Result<(), Vec<errors::ValidationIssue>> {
+        let issues = self
+            .headers
+            .duplicate_names()
+            .into_iter()
+            .filter(|name| SINGLETON_HEADERS.contains(&name.as_str()))
+            .map(errors::ValidationIssue::DuplicateHeader)
+            .collect::<Vec<errors::ValidationIssue>>();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    // Ok once the request can be handed to a handler, an incomplete request is not validated
+    pub fn is_complete_and_valid(&self) -> Result<(), Vec<errors::ValidationIssue>> {
+        if !self.body_complete() {
+            return Err(vec![errors::ValidationIssue::Incomplete]);
+        }
+        self.validate_request()
+    }
+
+    /*
+        https://www.rfc-editor.org/rfc/rfc7231#section-4.3.8
+
+        A client MUST NOT send a message body in a TRACE request.
+    */
+    // every check a complete HTTP/1.1 request has to pass before it is handed to an application,
+    // regardless of whether the request was parsed in strict mode
+    pub fn is_valid_http11_request(&self) -> bool {
+        if self.error.is_some() || !self.body_complete() {
+            return false;
+        }
+
+        let mut tokens = self.request_line.split(' ');
+        let method = match tokens.next().map(method::Method::parse) {
+            Some(Ok(method)) => method,
+            _ => return false,
+        };
+        let target = tokens.next().unwrap_or_default();
+        if target.is_empty() || tokens.count() != 1 || self.http_version() != Some((1, 1)) {
+            return false;
+        }
+
+        if self.headers.count("host") != 1 {
+            return false;
+        }
+        if self.headers.has("transfer-encoding") && self.headers.has("content-length") {
+            return false;
+        }
+        if self
+            .headers
+            .values
+            .iter()
+            .any(|h| h.key.is_empty() || !h.key.bytes().all(headers::is_tchar))
+        {
+            return false;
+        }
+        if method == method::Method::Trace && !self.body_slice().is_empty() {
+            return false;
+        }
+        self.expect_header_value().is_none() || self.expect_continue()
+    }
+
+    /*
+        https://www.rfc-editor.org/rfc/rfc7230#section-3.3.3
+
+        If this is a request message and none of the above are true, then the
+        message body length is zero (no message body is present).
+
+        So a request without Content-Length or Transfer-Encoding is complete as soon as
+        its headers are, whatever its method.
+    */
+    pub fn state(&self) -> ParseState {
+        if self.error.is_some() {
+            return ParseState::Failed;
+        }
+        let at = match self.headers_end {
+            HeadersEnd::FoundAt(at) => at,
+            _ => return ParseState::AwaitingHeaders,
+        };
+        if self.is_upgrade() {
+            return ParseState::AwaitingBody {
+                received: self.raw.len() - (at + HEADER_END.len()),
+                expected: None,
+            };
+        }
+        let state = match (&self.is_chunked, &self.content_length) {
+            (Chunked::Complete, _) => ParseState::Complete,
+            (Chunked::Processing, _) => ParseState::AwaitingBody {
+                received: self.chunk_decoder.decoded(),
+                expected: None,
+            },
+            (Chunked::Unset, ContentLength::Unset) => ParseState::Complete,
+            (Chunked::Unset, ContentLength::Value(content_length)) => {
+                if self.body_complete() {
+                    ParseState::Complete
+                } else {
+                    ParseState::AwaitingBody {
+                        received: self.received_body_len(at),
+                        expected: Some(*content_length),
+                    }
+                }
+            }
+        };
+        match state {
+            ParseState::AwaitingBody { expected, .. }
+                if self.received_body_len(at) == 0 && self.expect_continue() =>
+            {
+                ParseState::AwaitingContinue { expected }
+            }
+            state => state,
+        }
+    }
+
+    // a one line summary for logs, e.g. when a read times out on an incomplete request
+    pub fn debug_trace(&self) -> String {
+        let chunked = self.is_chunked != Chunked::Unset;
+        match self.state() {
+            ParseState::AwaitingHeaders => {
+                let at = match self.headers_end {
+                    HeadersEnd::Scanning(at) => at,
+                    _ => 0,
+                };
+                format!(
+                    "Request[state=ScanningHeaders at={}, raw={} bytes]",
+                    at,
+                    self.raw.len()
+                )
+            }
+            ParseState::AwaitingBody { received, expected } => match expected {
+                Some(expected) => format!(
+                    "Request[state=BodyIncomplete {}/{} bytes, chunked={}]",
+                    received, expected, chunked
+                ),
+                None => format!(
+                    "Request[state=BodyIncomplete {} bytes, chunked={}]",
+                    received, chunked
+                ),
+            },
+            ParseState::AwaitingContinue { .. } => {
+                format!("Request[state=AwaitingContinue, chunked={}]", chunked)
+            }
+            ParseState::Complete => format!(
+                "Request[state=Complete \"{}\", headers={}, body={} bytes, chunked={}]",
+                self.request_line,
+                self.headers.len(),
+                self.body_slice().len(),
+                chunked
+            ),
+            ParseState::Failed => format!(
+                "Request[state=Failed \"{}\", raw={} bytes]",
+                self.error
+                    .as_ref()
+                    .map(|e| e.to_string())
+                    .unwrap_or_default(),
+                self.raw.len()
+            ),
+        }
+    }
+
+    pub fn needs_more_data(&self) -> bool {
+        matches!(
+            self.state(),
+            ParseState::AwaitingHeaders
+                | ParseState::AwaitingBody { .. }
+                | ParseState::AwaitingContinue { .. }
+        )
+    }
+
+    pub fn parse_progress(&self) -> ParseProgress {
+        if let HeadersEnd::FoundAt(at) = self.headers_end {
+            return ParseProgress {
+                stage: Stage::Body,
+                bytes_in_stage: self.raw.len() - (at + HEADER_END.len())
+                    + self.discarded_body_bytes,
+            };
+        }
+        match self.raw.iter().position(|b| *b == b'\n') {
+            Some(at) => ParseProgress {
+                stage: Stage::Headers,
+                bytes_in_stage: self.raw.len() - (at + 1),
+            },
+            None => ParseProgress {
+                stage: Stage::RequestLine,
+                bytes_in_stage: self.raw.len(),
+            },
+        }
+    }
+
+    /*
+        https://www.rfc-editor.org/rfc/rfc7230#section-3.3.3
+
+        The framing is decided in order of precedence:
+        - no body can be complete before the headers are
+        - an upgrade request never completes, what follows its head is another protocol
+        - chunked framing wins whenever it is set (Content-Length is ignored)
+        - then a Content-Length body is complete once at least that many bytes arrived,
+          anything beyond it is counted by excess_bytes
+        - otherwise no body is expected at all
+    */
+    pub fn body_complete(&self) -> bool {
+        // a request that failed to parse never completes, see state
+        if self.error.is_some() {
+            return false;
+        }
+        let at = match self.headers_end {
+            HeadersEnd::FoundAt(at) => at,
+            HeadersEnd::Unset | HeadersEnd::Scanning(_) => return false,
+        };
+        if self.is_upgrade() {
+            return false;
+        }
+        match self.is_chunked {
+            Chunked::Complete => return true,
+            Chunked::Processing => return false,
+            Chunked::Unset => {}
+        }
+        match self.content_length {
+            ContentLength::Value(content_length) => self.received_body_len(at) >= content_length,
+            ContentLength::Unset => true,
+        }
+    }
+
+    // body bytes received so far for a Content-Length body
+    fn received_body_len(&self, headers_end: usize) -> usize {
+        if self.options.headers_only || self.body_taken {
+            return self.discarded_body_bytes;
+        }
+        self.raw[headers_end + HEADER_END.len()..].len()
+    }
+
+    // the length of the head including the blank line that ends it, once it has been received
+    pub fn header_len(&self) -> Option<usize> {
+        match self.headers_end {
+            HeadersEnd::FoundAt(at) => Some(at + HEADER_END.len()),
+            _ => None,
+        }
+    }
+
+    // body bytes received so far: the decoded payload for a chunked body, otherwise every
+    // byte after the head (which can include bytes past the end of the message)
+    pub fn body_len(&self) -> usize {
+        match self.headers_end {
+            HeadersEnd::FoundAt(_) if self.is_chunked != Chunked::Unset => {
+                self.chunk_decoder.decoded()
+            }
+            HeadersEnd::FoundAt(at) => self.received_body_len(at),
+            _ => 0,
+        }
+    }
+
+    // the class of the Content-Length, or of the body received so far when there is none
+    // (a chunked body keeps growing until its last chunk arrives)
+    pub fn size_class(&self) -> SizeClass {
+        let size = match self.content_length {
+            ContentLength::Value(content_length) if self.is_chunked == Chunked::Unset => {
+                content_length
+            }
+            _ => self.body_len(),
+        };
+        let thresholds = &self.options.size_class_thresholds;
+        match size {
+            0 => SizeClass::Empty,
+            size if size <= thresholds.small => SizeClass::Small,
+            size if size <= thresholds.medium => SizeClass::Medium,
+            size if size <= thresholds.large => SizeClass::Large,
+            _ => SizeClass::ExtraLarge,
+        }
+    }
+
+    // every byte given to update_raw so far, including body bytes dropped by headers_only
+    // and the empty lines skipped before the request line but not the CRs inserted by
+    // ParseOptions::lenient_line_endings
+    pub fn raw_len(&self) -> usize {
+        self.raw.len() + self.discarded_body_bytes + self.skipped_empty_lines
+            - self.inserted_carriage_returns
+    }
+
+    // the length the whole message will have on the wire once complete, when the head says so
+    // NOTE: None for chunked bodies since their length is only known once the last chunk arrives
+    pub fn expected_len(&self) -> Option<usize> {
+        match (&self.headers_end, &self.is_chunked, &self.content_length) {
+            (HeadersEnd::FoundAt(at), Chunked::Unset, ContentLength::Value(content_length)) => {
+                Some(at + HEADER_END.len() + content_length)
+            }
+            _ => None,
+        }
+    }
+
+    // bytes received after the end of this message, e.g. a pipelined request or a stray CRLF
+    // that some clients send after a body
+    pub fn excess_bytes(&self) -> usize {
+        match self.headers_end {
+            HeadersEnd::FoundAt(_) => {
+                (self.raw.len() + self.discarded_body_bytes).saturating_sub(self.consumed_len())
+            }
+            _ => 0,
+        }
+    }
+
+    // the length of this message as it is kept, head and framed body, so raw[consumed_len()..]
+    // is what followed it on the connection (unless headers_only dropped the body)
+    // NOTE: this counts the CRs inserted by ParseOptions::lenient_line_endings and not the
+    // skipped empty lines, on the wire the message took
+    // consumed_len() - inserted_carriage_returns + skipped_empty_lines bytes
+    pub fn consumed_len(&self) -> usize {
+        let at = match self.headers_end {
+            HeadersEnd::FoundAt(at) => at,
+            _ => return self.raw.len(),
+        };
+        let body = match (&self.is_chunked, &self.content_length) {
+            (Chunked::Unset, ContentLength::Unset) => 0,
+            (Chunked::Unset, ContentLength::Value(content_length)) => {
+                self.received_body_len(at).min(*content_length)
+            }
+            _ => self.chunk_decoder.consumed(),
+        };
+        at + HEADER_END.len() + body
+    }
+
+    pub fn update_raw(&mut self, data: &mut Vec<u8>) -> Result<(), errors::Errors> {
+        if let Some(e) = &self.error {
+            return Err(e.clone());
+        }
+        if self.body_taken {
+            return Err(errors::Errors::BodyTaken);
+        }
+        let mut result = self.parse_raw(data);
+        // headers_only streams from discard_body since nothing is kept to hand over here
+        if result.is_ok() && !self.options.headers_only {
+            result = self.stream_retained_body();
+        }
+        if let Err(e) = &result {
+            self.error = Some(e.clone());
+        }
+        result
+    }
+
+    // like update_raw but only what belongs to this request is taken from data, the number of
+    // bytes used is returned and data[used..] stays with the caller, e.g. the next request
+    // NOTE: meant to be used instead of update_raw, excess bytes it left in raw are dropped
+    pub fn feed(&mut self, data: &[u8]) -> Result<usize, errors::Errors> {
+        let before = self.raw_len();
+        self.update_raw(&mut data.to_vec())?;
+        // headers_only never counts the bytes past the end of the body
+        let taken = self.raw_len() - before;
+        let excess = self.excess_bytes();
+        self.raw.truncate(self.raw.len() - excess);
+        Ok(taken.saturating_sub(excess))
+    }
+
+    fn parse_raw(&mut self, data: &mut Vec<u8>) -> Result<(), errors::Errors> {
+        if self.options.headers_only && matches!(self.headers_end, HeadersEnd::FoundAt(_)) {
+            return self.discard_body(data);
+        }
+        self.raw.append(data);
+        if !matches!(self.headers_end, HeadersEnd::FoundAt(_)) {
+            self.skip_empty_lines();
+        }
+
+        match self.headers_end {
+            HeadersEnd::Unset => self.attempt_header_parsing(0)?,
+            HeadersEnd::Scanning(index) => self.attempt_header_parsing(index)?,
+            HeadersEnd::FoundAt(_) => {}
+        }
+        if self.options.headers_only {
+            if let HeadersEnd::FoundAt(at) = self.headers_end {
+                let body = self.raw.split_off(at + HEADER_END.len());
+                return self.discard_body(&body);
+            }
+        }
+        self.decode_chunks()
+    }
+
+    // headers_only: count the body bytes that belong to this request and let the rest go
+    fn discard_body(&mut self, data: &[u8]) -> Result<(), errors::Errors> {
+        let used = match (&self.is_chunked, &self.content_length) {
+            (Chunked::Processing, _) => {
+                // a callback gets the decoded payload, which is dropped once it has been handed over
+                self.chunk_decoder.discard_body = self.body_callback.is_none();
+                let used = self.chunk_decoder.feed(data)?;
+                let decoded = std::mem::take(&mut self.chunk_decoder.body);
+                self.stream_body(&decoded)?;
+                if self.chunk_decoder.is_complete() {
+                    self.is_chunked = Chunked::Complete;
+                }
+                used
+            }
+            (Chunked::Unset, ContentLength::Value(content_length)) => {
+                let used = (content_length - self.discarded_body_bytes).min(data.len());
+                self.stream_body(&data[..used])?;
+                used
+            }
+            _ => 0,
+        };
+        self.discarded_body_bytes += used;
+        Ok(())
+    }
+
+    // hand any body bytes that the chunk decoder has not seen yet over to it
+    fn decode_chunks(&mut self) -> Result<(), errors::Errors> {
+        if self.is_chunked != Chunked::Processing {
+            return Ok(());
+        }
+        if let HeadersEnd::FoundAt(at) = self.headers_end {
+            let start = at + HEADER_END.len() + self.chunk_decoder.consumed();
+            self.chunk_decoder.feed(&self.raw[start..])?;
+            if self.chunk_decoder.is_complete() {
+                self.is_chunked = Chunked::Complete;
+            }
+        }
+        Ok(())
+    }
+
+    fn attempt_header_parsing(&mut self, mut at: usize) -> Result<(), errors::Errors> {
+        while at < self.raw.len() {
+            match self.raw[at] {
+                /*
+                    https://www.rfc-editor.org/rfc/rfc7230#section-3.5
+
+                    Although the line terminator for the start-line and header fields is
+                    the sequence CRLF, a recipient MAY recognize a single LF as a line
+                    terminator and ignore any preceding CR.
+
+                    Bare LFs are only accepted when the request has been created with
+                    Request::lenient(), otherwise they are rejected outright.
+                */
+                b'\n' => {
+                    let after_cr = at > 0 && self.raw[at - 1] == b'\r';
+                    if !after_cr && !self.options.lenient_line_endings {
+                        return Err(errors::Errors::BareLineFeed);
+                    }
+
+                    if self.options.lenient_line_endings {
+                        // the line that just ended was empty: "\n\n" or "\n\r\n"
+                        let line_start = if after_cr { at - 1 } else { at };
+                        if line_start > 0 && self.raw[line_start - 1] == b'\n' {
+                            self.normalize_line_endings(at);
+                            break;
+                        }
+                    } else if at >= 3 && self.raw[at - 3..=at] == *HEADER_END {
+                        self.headers_end = HeadersEnd::FoundAt(at - 3);
+                        break;
+                    }
+                }
+                /*
+                    https://www.rfc-editor.org/rfc/rfc7230#section-3.2.4
+
+                    A CR that is not part of a CRLF is never valid in the header section
+                    (field values may not contain CR) regardless of how lenient we are.
+                */
+                b'\r' if at + 1 < self.raw.len() && self.raw[at + 1] != b'\n' => {
+                    return Err(errors::Errors::BareCarriageReturn);
+                }
+                _ => {}
+            }
+            at += 1;
+        }
+
+        if let HeadersEnd::FoundAt(_) = self.headers_end {
+            self.parse_and_fill_headers()?;
+        } else {
+            // raw data might come in that splits the HEADER_END in two:
+            // EG:
+            //  previous append to raw: "\r"
+            //  next append to raw: "\n\r\n"
+            //
+            // as a result, backup enough to find a complete HEADER_END
+            // (without going past the start of raw when only a few bytes have arrived)
+            self.headers_end = HeadersEnd::Scanning(at.saturating_sub(HEADER_END.len()));
+        }
+        Ok(())
+    }
+
+    /*
+        https://www.rfc-editor.org/rfc/rfc7230#section-3.5
+
+        In the interest of robustness, a server that is expecting to receive
+        and parse a request-line SHOULD ignore at least one empty line (CRLF)
+        received prior to the request-line.
+    */
+    fn skip_empty_lines(&mut self) {
+        let mut skip = 0;
+        loop {
+            if self.raw[skip..].starts_with(LINE_END) {
+                skip += LINE_END.len();
+            } else if self.options.lenient_line_endings && self.raw[skip..].starts_with(b"\n") {
+                skip += 1;
+            } else {
+                break;
+            }
+        }
+        if skip > 0 {
+            self.raw.drain(..skip);
+            self.skipped_empty_lines += skip;
+            self.headers_end = HeadersEnd::Unset;
+        }
+    }
+
+    // rewrite the header section raw[0..=end] so that every line ends in CRLF,
+    // leaving the body (everything after end) untouched
+    fn normalize_line_endings(&mut self, end: usize) {
+        let mut normalized = Vec::with_capacity(end + 1);
+        for (i, byte) in self.raw[0..=end].iter().enumerate() {
+            if *byte == b'\n' && (i == 0 || self.raw[i - 1] != b'\r') {
+                normalized.push(b'\r');
+            }
+            normalized.push(*byte);
+        }
+        let header_end = normalized.len() - HEADER_END.len();
+        self.inserted_carriage_returns = normalized.len() - (end + 1);
+        self.raw.splice(0..=end, normalized);
+        self.headers_end = HeadersEnd::FoundAt(header_end);
+    }
+
+    fn parse_and_fill_headers(&mut self) -> Result<(), errors::Errors> {
+        if let HeadersEnd::FoundAt(end) = self.headers_end {
+            let header_chunk = self.raw[0..end].to_vec();
+
+            let mut newline_indices = vec![];
+            let mut from = 0;
+            while let Some(i) = util::scan_for_crlf(&header_chunk, from) {
+                newline_indices.push(i);
+                from = i + LINE_END.len();
+            }
+            newline_indices.push(header_chunk.len());
+
+            let mut newline = newline_indices.iter();
+            let mut at = newline.next().unwrap();
+
+            match String::from_utf8(header_chunk[0..*at].to_owned()) {
+                // TODO: check that the method and request-target of the request line are valid
+                Ok(s) => self.request_line = s,
+                Err(e) => return Err(errors::Errors::Parse(e)),
+            };
+            /*
+                https://www.rfc-editor.org/rfc/rfc7230#section-3.1.1
+
+                request-line   = method SP request-target SP HTTP-version CRLF
+            */
+            // the empty lines before the request line have been skipped, so this is a head
+            // that starts with a header, or with a line of spaces
+            let tokens = self.request_line.split(' ').collect::<Vec<&str>>();
+            match tokens.as_slice() {
+                ["", ..] => return Err(errors::Errors::RequestLine("missing method")),
+                [_] | [_, _] => return Err(errors::Errors::RequestLine("missing HTTP-version")),
+                [_, _, version] => {
+                    if parse_http_version(version).is_none() {
+                        return Err(errors::Errors::RequestLine("invalid HTTP-version"));
+                    }
+                }
+                _ => {
+                    return Err(errors::Errors::RequestLine(
+                        "expected method SP request-target SP HTTP-version",
+                    ))
+                }
+            }
+
+            loop {
+                let sindex = at + LINE_END.len();
+                let mut eindex = match newline.next() {
+                    Some(eindex) => eindex,
+                    None => break,
+                };
+
+                let mut skip_fold_spaces: Vec<usize> = vec![sindex, *eindex];
+
+                loop {
+                    if eindex == &header_chunk.len() {
+                        break;
+                    }
+
+                    /*
+                      https://www.rfc-editor.org/rfc/rfc7230
+
+                      A proxy or gateway that receives an obs-fold in a response message
+                      that is not within a message/http container MUST either discard the
+                      message and replace it with a 502 (Bad Gateway) response, preferably
+                      with a representation explaining that unacceptable line folding was
+                      received, or replace each received obs-fold with one or more SP
+                      octets prior to interpreting the field value or forwarding the
+                      message downstream.
+
+                      https://www.ietf.org/rfc/rfc2616.txt
+
+                      All linear white space, including folding, has the same semantics as SP. A
+                      recipient MAY replace any linear white space with a single SP before
+                      interpreting the field value or forwarding the message downstream.
+
+                      LWS            = [CRLF] 1*( SP | HT )
+
+                      In other words, one or more spaces or tabs must be replaced with a single space.
+                    */
+
+                    // evaluate the first byte(s) in the next line
+                    // to determine if we are dealing with a "line folded" header
+                    // a fold holding only spaces or tabs can run up to the end of the head
+                    let mut offset = 0;
+                    while header_chunk
+                        .get(eindex + LINE_END.len() + offset)
+                        .is_some_and(|b| *b == b'\t' || *b == b' ')
+                    {
+                        offset += 1;
+                    }
+                    let is_line_fold = offset > 0;
+
+                    if is_line_fold && self.options.strict {
+                        return Err(errors::Errors::ObsoleteLineFolding);
+                    }
+                    if is_line_fold {
+                        let sindex = eindex + LINE_END.len() + offset;
+                        eindex = match newline.next() {
+                            Some(eindex) => eindex,
+                            None => break,
+                        };
+                        skip_fold_spaces.push(sindex);
+                        skip_fold_spaces.push(*eindex);
+                    } else {
+                        break;
+                    }
+                }
+                at = eindex;
+
+                // each fold, with the spaces and tabs around it, becomes a single space
+                // and a fold of only spaces or tabs is an empty continuation that adds nothing
+                let mut header: Vec<u8> = vec![];
+                for i in 0..skip_fold_spaces.len() {
+                    if i % 2 == 1 {
+                        continue;
+                    }
+                    if i > 0 && skip_fold_spaces[i] == skip_fold_spaces[i + 1] {
+                        continue;
+                    }
+                    if i > 0 {
+                        while header.last().is_some_and(|b| *b == b' ' || *b == b'\t') {
+                            header.pop();
+                        }
+                        header.push(b' ');
+                    }
+                    let mut chunk =
+                        header_chunk[skip_fold_spaces[i]..skip_fold_spaces[i + 1]].to_owned();
+                    header.append(&mut chunk);
+                }
+
+                let header = match &self.interner {
+                    Some(interner) => match interner.lock() {
+                        Ok(mut interner) => headers::Header::new_interned(header, &mut interner)?,
+                        Err(_) => headers::Header::new(header)?,
+                    },
+                    None => headers::Header::new(header)?,
+                };
+
+                /*
+                    https://www.rfc-editor.org/rfc/rfc7231#section-5.1.1
+
+                    Expect  = "100-continue"
+
+                    A server that receives an Expect field-value other than 100-continue
+                    MAY respond with a 417 (Expectation Failed) status code to indicate
+                    that the unexpected expectation cannot be met.
+                */
+                if self.options.strict
+                    && header.name_is("expect")
+                    && !header.trimmed_value().eq_ignore_ascii_case("100-continue")
+                {
+                    return Err(errors::Errors::UnknownExpectValue(
+                        header.trimmed_value().to_owned(),
+                    ));
+                }
+
+                if header.name_is("content-length") {
+                    match self.content_length {
+                        ContentLength::Value(_) => {
+                            return Err(errors::Errors::Header(
+                                "Content-Length header must appear only once".to_owned(),
+                            ))
+                        }
+                        ContentLength::Unset => {
+                            self.content_length =
+                                ContentLength::Value(headers::parse_content_length(
+                                    &header.value,
+                                    self.options.max_content_length.unwrap_or(usize::MAX),
+                                )?);
+                        }
+                    }
+                }
+
+                // check for chunked state: Transfer-Encoding: gzip, chunked
+                if header.name_is("transfer-encoding") {
+                    /*
+                        https://www.rfc-editor.org/rfc/rfc7230#section-3.3.1
+
+                        Transfer-Encoding = 1#transfer-coding
+
+                        If any transfer coding other than chunked is applied to a request
+                        payload body, the sender MUST apply chunked as the final transfer
+                        coding to ensure that the message is properly framed.  A sender MUST
+                        NOT apply chunked more than once to a message body.
+
+                        https://www.rfc-editor.org/rfc/rfc7230#section-3.2.2
+
+                        A recipient MAY combine multiple header fields with the same field
+                        name into one "field-name: field-value" pair, without changing the
+                        semantics of the message, by appending each subsequent field value to
+                        the combined field value in order, separated by a comma.
+                    */
+                    // the header is not in self.headers yet so these are the earlier codings
+                    let mut codings = self.headers.transfer_encodings();
+                    codings.extend(
+                        header
+                            .value_list()
+                            .into_iter()
+                            .map(|c| c.to_ascii_lowercase()),
+                    );
+                    let chunked = codings.iter().filter(|c| *c == "chunked").count();
+                    if chunked > 1 {
+                        return Err(errors::Errors::Header(
+                            "chunked must appear only once in Transfer-Encoding".to_owned(),
+                        ));
+                    }
+                    if chunked == 1 {
+                        if codings.last().map(|c| c.as_str()) != Some("chunked") {
+                            return Err(errors::Errors::Header(format!(
+                                "chunked must appear at the very end of the Transfer-Encoding header value: {}",
+                                codings.join(", ")
+                            )));
+                        }
+                        self.is_chunked = Chunked::Processing;
+                    }
+                }
+
+                let content_length_set = !matches!(self.content_length, ContentLength::Unset);
+                let is_chunked_set = !matches!(self.is_chunked, Chunked::Unset);
+                if content_length_set && is_chunked_set {
+                    return Err(errors::Errors::Header(
+                        "Transfer-Encoding and Content-Length headers are mutually exclusive"
+                            .to_owned(),
+                    ));
+                }
+
+                self.headers.values.push(header.clone());
+            }
+
+            /*
+                https://www.rfc-editor.org/rfc/rfc7230#section-3.3.3
+
+                If a Transfer-Encoding header field is present in a request and the
+                chunked transfer coding is not the final encoding, the message body
+                length cannot be determined reliably; the server MUST respond with
+                the 400 (Bad Request) status code and then close the connection.
+
+                Checked once the whole head is in since the codings can be spread over
+                several headers, e.g. "Transfer-Encoding: gzip" then "Transfer-Encoding: chunked".
+            */
+            if self.headers.has("transfer-encoding") {
+                if !matches!(self.content_length, ContentLength::Unset) {
+                    return Err(errors::Errors::Header(
+                        "Transfer-Encoding and Content-Length headers are mutually exclusive"
+                            .to_owned(),
+                    ));
+                }
+                if self.is_chunked == Chunked::Unset {
+                    return Err(errors::Errors::Header(format!(
+                        "chunked must be the final coding of the Transfer-Encoding header value: {}",
+                        self.headers.transfer_encodings().join(", ")
+                    )));
+                }
+            }
+        } else {
+            return Err(errors::Errors::CannotFillHeaders);
+        }
+        if self.options.strict {
+            self.validate_host()?;
+        }
+        Ok(())
+    }
+
+    /*
+        https://www.rfc-editor.org/rfc/rfc7230#section-5.4
+
+        A server MUST respond with a 400 (Bad Request) status code to any
+        HTTP/1.1 request message that lacks a Host header field and to any
+        request message that contains more than one Host header field or a
+        Host header field with an invalid field-value.
+    */
+    fn validate_host(&self) -> Result<(), errors::Errors> {
+        let hosts = self.headers.count("host");
+        if hosts > 1 {
+            return Err(errors::Errors::Header("multiple Host headers".to_owned()));
+        }
+        let http_1_1 = matches!(self.http_version(), Some(version) if version >= (1, 1));
+        if hosts == 0 && http_1_1 {
+            return Err(errors::Errors::Header("missing Host header".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunked() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n7\r\npedia i\r\nB\r\nn \r\nchunks.\r\n0\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.is_chunked, Chunked::Complete);
+        assert!(r.body_complete());
+        assert_eq!(r.body(), b"Wikipedia in \r\nchunks.".to_vec());
+    }
+
+    #[test]
+    fn test_chunked_trailers_streamed() {
+        // https://stackoverflow.com/questions/5590791/http-chunked-encoding-need-an-example-of-trailer-mentioned-in-spec
+        let wire = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nTrailer: Expires\r\n\r\n7\r\nMozilla\r\n9\r\nDeveloper\r\n7\r\nNetwork\r\n0\r\nExpires: Wed, 21 Oct 2015 07:28:00 GMT\r\n\r\n";
+
+        let mut r = Request::default();
+        for chunk in wire.as_bytes().chunks(5) {
+            assert!(!r.body_complete());
+            let res = r.update_raw(&mut chunk.to_vec());
+            assert_eq!(res, Ok(()));
+        }
+        assert!(r.body_complete());
+        assert_eq!(r.body(), b"MozillaDeveloperNetwork".to_vec());
+        assert_eq!(
+            r.trailers().values[0].to_string(),
+            "Expires: Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+        assert_eq!(r.dump(), wire.as_bytes().to_vec());
+    }
+
+    #[cfg(feature = "extensions")]
+    #[test]
+    fn test_chunk_extensions() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3;sig=abc\r\nabc\r\n0\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.chunk_extensions().len(), 2);
+        assert_eq!(r.chunk_extensions()[0][0].name, "sig");
+        assert_eq!(r.chunk_extensions()[0][0].value, Some("abc".to_owned()));
+        assert!(r.chunk_extensions()[1].is_empty());
+    }
+
+    #[test]
+    fn test_content_length() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nContent-Length: 4\r\nHere: here\r\n\r\nBODY"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.headers.values[0].to_string(), "Content-Length: 4");
+        assert_eq!(r.headers.values[1].to_string(), "Here: here");
+        assert_eq!(r.headers.values.len(), 2);
+        assert_eq!(r.content_length, ContentLength::Value(4));
+        assert_eq!(r.body(), vec![b'B', b'O', b'D', b'Y']);
+        assert!(r.body_complete());
+    }
+
+    #[test]
+    fn test_content_length_value() {
+        let content_length = |value: &str, options: ParseOptions| {
+            let mut r = Request {
+                options,
+                ..Default::default()
+            };
+            let raw = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", value);
+            r.update_raw(&mut raw.into_bytes())
+                .map(|_| r.content_length)
+        };
+        assert_eq!(
+            content_length("007", ParseOptions::default()),
+            Ok(ContentLength::Value(7))
+        );
+        assert_eq!(
+            content_length("42, 42", ParseOptions::default()),
+            Ok(ContentLength::Value(42))
+        );
+        for value in ["4 2", "42abc", "+42", "42, 43"] {
+            assert_eq!(
+                content_length(value, ParseOptions::default()),
+                Err(errors::Errors::InvalidContentLength(value.to_owned()))
+            );
+        }
+
+        let limited = ParseOptions {
+            max_content_length: Some(1024),
+            ..Default::default()
+        };
+        assert_eq!(
+            content_length("1024", limited.clone()),
+            Ok(ContentLength::Value(1024))
+        );
+        assert_eq!(
+            content_length("1025", limited),
+            Err(errors::Errors::ContentLengthTooLarge(1025))
+        );
+    }
+
+    #[test]
+    fn test_body_slice() {
+        let mut r = Request::default();
+        assert_eq!(r.body_slice(), b"");
+
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBO"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.body_slice(), b"BO");
+
+        let res = r.update_raw(&mut b"DY".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.body_slice(), b"BODY");
+        assert_eq!(r.body_slice(), r.body().as_slice());
+    }
+
+    #[test]
+    fn test_body_incomplete() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nContent-Length: 5\r\nHere: here\r\n\r\nBODY"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.headers.values[0].to_string(), "Content-Length: 5");
+        assert_eq!(r.headers.values[1].to_string(), "Here: here");
+        assert_eq!(r.headers.values.len(), 2);
+        assert_eq!(r.content_length, ContentLength::Value(5));
+        assert!(!r.body_complete());
+
+        let res = r.update_raw(&mut "S".as_bytes().to_vec());
+        assert_eq!(res, Ok(()));
+        assert!(r.body_complete());
+    }
+
+    #[test]
+    fn test_content_length_zero() {
+        let mut r = Request::default();
+        let res = r.update_raw(&mut "GET / HTTP/1.1\r\nHere: here\r\n".as_bytes().to_vec());
+        assert_eq!(res, Ok(()));
+        assert!(!r.has_full_headers());
+        assert!(!r.body_complete());
+
+        let res = r.update_raw(&mut "More: more\r\nFinal: final\r\n\r\n".as_bytes().to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.headers.values[0].to_string(), "Here: here");
+        assert_eq!(r.headers.values[1].to_string(), "More: more");
+        assert_eq!(r.headers.values[2].to_string(), "Final: final");
+        assert_eq!(r.headers.values.len(), 3);
+        assert_eq!(r.content_length, ContentLength::Unset);
+        assert!(r.body_complete());
+    }
+
+    #[test]
+    fn test_headers_complete_before_body() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut b"POST / HTTP/1.1\r\nAuthorization: x\r\nContent-Length: 4\r".to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert!(!r.has_full_headers());
+        assert!(!r.headers_complete());
+
+        let res = r.update_raw(&mut b"\n\r\nBO".to_vec());
+        assert_eq!(res, Ok(()));
+        assert!(r.has_full_headers());
+        assert!(r.headers_complete());
+        assert!(!r.body_complete());
+        assert_eq!(r.headers.get("authorization").unwrap().value, "x");
+
+        let res = r.update_raw(&mut b"DY".to_vec());
+        assert_eq!(res, Ok(()));
+        assert!(r.has_full_headers());
+        assert!(r.body_complete());
+    }
+
+    #[test]
+    fn test_take_body() {
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBO";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.take_body(), None);
+
+        assert_eq!(r.update_raw(&mut b"DYGET".to_vec()), Ok(()));
+        assert_eq!(r.take_body(), Some(b"BODY".to_vec()));
+        // the start of a pipelined request stays
+        assert_eq!(
+            r.raw,
+            b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nGET".to_vec()
+        );
+        assert!(r.body_complete());
+        assert_eq!(r.body(), b"".to_vec());
+        assert_eq!(
+            r.dump(),
+            b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\n".to_vec()
+        );
+        assert_eq!(r.excess_bytes(), 3);
+        assert_eq!(r.raw[r.raw.len() - r.excess_bytes()..], b"GET"[..]);
+
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\nabXY";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.take_body(), Some(b"ab".to_vec()));
+        assert_eq!(r.excess_bytes(), 2);
+        assert!(r.raw.ends_with(b"XY"));
+        assert_eq!(r.take_body(), None);
+        assert_eq!(
+            r.update_raw(&mut b"x".to_vec()),
+            Err(errors::Errors::BodyTaken)
+        );
+        assert_eq!(r.state(), ParseState::Complete);
+
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\nGET / HTTP/1.1\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.take_body(), Some(b"hi".to_vec()));
+        assert_eq!(r.body(), b"".to_vec());
+        assert!(r.body_complete());
+        assert_eq!(r.excess_bytes(), 18);
+        let mut next = Request::default();
+        let rest = r.raw[r.raw.len() - r.excess_bytes()..].to_vec();
+        assert_eq!(next.update_raw(&mut rest.clone()), Ok(()));
+        assert!(next.body_complete());
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"GET / HTTP/1.1\r\n\r\n".to_vec()),
+            Ok(())
+        );
+        assert_eq!(r.take_body(), Some(vec![]));
+    }
+
+    #[test]
+    fn test_on_body() {
+        let fragments: [&[u8]; 4] = [
+            b"POST / HTTP/1.1\r\nContent-Length: 11\r\n",
+            b"\r\nhello",
+            b" wor",
+            b"ldGET / HTTP/1.1\r\n",
+        ];
+        let chunked: [&[u8]; 3] = [
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel",
+            b"lo\r\n6\r\n world\r",
+            b"\n0\r\n\r\n",
+        ];
+        for headers_only in [false, true] {
+            for fragments in [&fragments[..], &chunked[..]] {
+                let received = Arc::new(Mutex::new(vec![]));
+                let calls = Arc::new(Mutex::new(0));
+                let mut r = Request {
+                    options: ParseOptions {
+                        headers_only,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                let (sink, count) = (received.clone(), calls.clone());
+                r.on_body(move |bytes| {
+                    sink.lock().unwrap().extend_from_slice(bytes);
+                    *count.lock().unwrap() += 1;
+                });
+                for fragment in fragments {
+                    assert_eq!(r.update_raw(&mut fragment.to_vec()), Ok(()));
+                }
+                assert!(r.body_complete());
+                assert_eq!(*received.lock().unwrap(), b"hello world".to_vec());
+                // handed over as it arrived, not once at the end
+                assert!(*calls.lock().unwrap() > 1);
+                let kept = if headers_only {
+                    vec![]
+                } else {
+                    b"hello world".to_vec()
+                };
+                assert_eq!(r.body(), kept);
+            }
+        }
+    }
+
+    #[test]
+    fn test_body_writer() {
+        // counts what is written instead of keeping it
+        struct Sink(Arc<Mutex<(usize, u64)>>);
+        impl std::io::Write for Sink {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                let mut sink = self.0.lock().unwrap();
+                sink.0 += buf.len();
+                sink.1 += buf.iter().map(|b| *b as u64).sum::<u64>();
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        const SIZE: usize = 10 * 1024 * 1024;
+        let body = (0..SIZE).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+        let sum = body.iter().map(|b| *b as u64).sum::<u64>();
+        let mut chunked = vec![];
+        for chunk in body.chunks(100_000) {
+            chunked.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+            chunked.extend_from_slice(chunk);
+            chunked.extend_from_slice(b"\r\n");
+        }
+        chunked.extend_from_slice(b"0\r\n\r\n");
+
+        let cases = [
+            (format!("Content-Length: {}", SIZE), &body),
+            ("Transfer-Encoding: chunked".to_owned(), &chunked),
+        ];
+        for (framing, body) in cases {
+            let head = format!("POST /upload HTTP/1.1\r\n{}\r\n\r\n", framing);
+            let mut wire = head.clone().into_bytes();
+            wire.extend_from_slice(body);
+
+            let written = Arc::new(Mutex::new((0, 0)));
+            let mut r = Request {
+                options: ParseOptions {
+                    headers_only: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            r.body_writer(Sink(written.clone()));
+            for fragment in wire.chunks(8 * 1024) {
+                assert_eq!(r.update_raw(&mut fragment.to_vec()), Ok(()));
+                assert!(r.raw.len() <= head.len());
+            }
+            assert!(r.body_complete());
+            assert_eq!(*written.lock().unwrap(), (SIZE, sum));
+            assert_eq!(r.body_len(), SIZE);
+            assert!(r.body().is_empty());
+            // just the head, the body went to the writer
+            assert_eq!(r.dump(), head.into_bytes());
+        }
+    }
+
+    #[test]
+    fn test_body_writer_error() {
+        struct Full;
+        impl std::io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk full"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        for headers_only in [false, true] {
+            let mut r = Request {
+                options: ParseOptions {
+                    headers_only,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            r.body_writer(Full);
+            let raw = b"POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\n";
+            assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+            let e = errors::Errors::Io("disk full".to_owned());
+            assert_eq!(r.update_raw(&mut b"hi".to_vec()), Err(e.clone()));
+            assert_eq!(r.error, Some(e));
+        }
+    }
+
+    #[test]
+    fn test_body_lines() {
+        let body = "{\"a\":1}\r\n{\"b\":2}\n\n{\"c\":3}\n";
+        let mut r = Request::default();
+        let raw = format!(
+            "POST / HTTP/1.1\r\nContent-Type: application/x-ndjson\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        assert_eq!(
+            r.update_raw(&mut raw.as_bytes()[..raw.len() - 1].to_vec()),
+            Ok(())
+        );
+        assert!(r.body_lines().is_none());
+
+        assert_eq!(r.update_raw(&mut b"\n".to_vec()), Ok(()));
+        assert_eq!(
+            r.body_lines().unwrap().collect::<Vec<&str>>(),
+            vec!["{\"a\":1}", "{\"b\":2}", "", "{\"c\":3}"]
+        );
+
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\n\xff\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(r.body_lines().is_none());
+    }
+
+    #[test]
+    fn test_multi_line_header() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "GET / HTTP/1.1\r\nFirst: wrapp\r\n   ing\r\n\ttest\r\nSecond: wrapp\r\n    ing\r\n\ttest\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.headers.values[0].to_string(), "First: wrapp ing test");
+        assert_eq!(r.headers.values[1].to_string(), "Second: wrapp ing test");
+        assert!(r.body_complete());
+
+        let mut r = Request::default();
+        let raw = b"GET / HTTP/1.1\r\nFolded: a \t\r\n\t \t  b\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.headers.values[0].value, "a b");
+
+        // folds of only spaces or tabs, at the end of the head or before another header
+        let cases: [(&[u8], &str); 3] = [
+            (b"GET / HTTP/1.1\r\nA: b\r\n   \r\n\r\n", "A: b"),
+            (b"GET / HTTP/1.1\r\nA: b\r\n\t\r\n\r\n", "A: b"),
+            (
+                b"GET / HTTP/1.1\r\nA: b\r\n \t\r\n c\r\nD: e\r\n\r\n",
+                "A: b c",
+            ),
+        ];
+        for (raw, header) in cases {
+            let mut r = Request::default();
+            assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+            assert_eq!(r.headers.values[0].to_string(), header);
+        }
+
+        let mut r = Request::strict();
+        let raw = b"GET / HTTP/1.1\r\nHost: x\r\nFolded: a\r\n b\r\n\r\n";
+        assert_eq!(
+            r.update_raw(&mut raw.to_vec()),
+            Err(errors::Errors::ObsoleteLineFolding)
+        );
+    }
+
+    #[test]
+    fn test_bad_chunked_header() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "GET / HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(
+            res,
+            Err(errors::Errors::Header(
+                "chunked must appear at the very end of the Transfer-Encoding header value: chunked, gzip"
+                    .to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_multiple_transfer_encoding_headers() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\nTransfer-Encoding: Chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.is_chunked, Chunked::Complete);
+        assert_eq!(r.body(), b"hi".to_vec());
+
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nTransfer-Encoding: gzip\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(
+            res,
+            Err(errors::Errors::Header(
+                "chunked must appear at the very end of the Transfer-Encoding header value: chunked, gzip"
+                    .to_owned()
+            ))
+        );
+
+        for raw in [
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nTransfer-Encoding: chunked\r\n\r\n",
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked, chunked\r\n\r\n",
+        ] {
+            let mut r = Request::default();
+            assert_eq!(
+                r.update_raw(&mut raw.as_bytes().to_vec()),
+                Err(errors::Errors::Header(
+                    "chunked must appear only once in Transfer-Encoding".to_owned()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_chunked_header_whitespace() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nTransfer-Encoding: gzip , chunked\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.is_chunked, Chunked::Processing);
+
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nTransfer-Encoding: chunked \r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.is_chunked, Chunked::Processing);
+
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(
+            res,
+            Err(errors::Errors::Header(
+                "chunked must appear at the very end of the Transfer-Encoding header value: chunked, gzip"
+                    .to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_chunked_is_matched_exactly() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n0\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.is_chunked, Chunked::Complete);
+
+        // neither is chunked, so neither can frame a request
+        for (raw, codings) in [
+            (
+                "POST / HTTP/1.1\r\nTransfer-Encoding: chunkednotreally\r\n\r\n",
+                "chunkednotreally",
+            ),
+            (
+                "POST / HTTP/1.1\r\nTransfer-Encoding: x-custom;p=\"chunked, gzip\"\r\n\r\n",
+                "x-custom;p=chunked, gzip",
+            ),
+        ] {
+            let mut r = Request::default();
+            assert_eq!(
+                r.update_raw(&mut raw.as_bytes().to_vec()),
+                Err(errors::Errors::Header(format!(
+                    "chunked must be the final coding of the Transfer-Encoding header value: {}",
+                    codings
+                )))
+            );
+        }
+    }
+
+    #[test]
+    fn test_not_chunked_with_content_length() {
+        // codings that only resemble chunked don't make Content-Length framing safe
+        for coding in ["xchunked", "gzip", "chunkednotreally", "gzip, chunked"] {
+            let raw = format!(
+                "POST / HTTP/1.1\r\nTransfer-Encoding: {}\r\nContent-Length: 3\r\n\r\nabc",
+                coding
+            );
+            let mut r = Request::default();
+            assert_eq!(
+                r.update_raw(&mut raw.into_bytes()),
+                Err(errors::Errors::Header(
+                    "Transfer-Encoding and Content-Length headers are mutually exclusive"
+                        .to_owned()
+                )),
+                "{}",
+                coding
+            );
+        }
+    }
+
+    #[test]
+    fn test_final_coding_not_chunked() {
+        // framed by Content-Length the rest would be a smuggled second request
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\nContent-Length: 3\r\n\r\nabcGET /smuggled HTTP/1.1\r\n\r\n";
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut raw.to_vec()),
+            Err(errors::Errors::Header(
+                "Transfer-Encoding and Content-Length headers are mutually exclusive".to_owned()
+            ))
+        );
+
+        // framed by nothing the chunked payload would be read as the next request
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n3\r\nabc\r\n0\r\n\r\n";
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut raw.to_vec()),
+            Err(errors::Errors::Header(
+                "chunked must be the final coding of the Transfer-Encoding header value: gzip"
+                    .to_owned()
+            ))
+        );
+
+        // an empty value has no final coding either
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"POST / HTTP/1.1\r\nTransfer-Encoding: \r\n\r\n".to_vec()),
+            Err(errors::Errors::Header(
+                "chunked must be the final coding of the Transfer-Encoding header value: "
+                    .to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_mutually_exclusive() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "GET / HTTP/1.1\r\nContent-Length: 10\r\nTransfer-Encoding: chunked\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(
+            res,
+            Err(errors::Errors::Header(
+                "Transfer-Encoding and Content-Length headers are mutually exclusive".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_body() {
+        let mut r = Request::default();
+
+        let res = r.update_raw(&mut "POST / HTTP/1.1\r".as_bytes().to_vec());
+        assert_eq!(res, Ok(()));
+        let res = r.update_raw(&mut "\nContent-L".as_bytes().to_vec());
+        assert_eq!(res, Ok(()));
+        let res = r.update_raw(&mut "ength: 4\r\n".as_bytes().to_vec());
+        assert_eq!(res, Ok(()));
+        let res = r.update_raw(&mut "\r\nBODY".as_bytes().to_vec());
+        assert_eq!(res, Ok(()));
+
+        assert_eq!(r.headers.values[0].to_string(), "Content-Length: 4");
+        assert!(r.body_complete());
+    }
+
+    #[test]
+    fn test_http_version() {
+        let parse = |line: &str| {
+            let mut r = Request::default();
+            r.update_raw(&mut format!("{}\r\n\r\n", line).into_bytes())
+                .map(|_| (r.version_major(), r.version_minor()))
+        };
+        assert_eq!(parse("GET / HTTP/1.1"), Ok((Some(1), Some(1))));
+        assert_eq!(parse("GET / HTTP/1.0"), Ok((Some(1), Some(0))));
+        for line in [
+            "GET / HTTP/1",
+            "GET / HTTPS/1.1",
+            "GET / HTTP/1.10",
+            "GET / http/1.1",
+        ] {
+            assert_eq!(
+                parse(line),
+                Err(errors::Errors::RequestLine("invalid HTTP-version")),
+                "{}",
+                line
+            );
+        }
+        assert_eq!(
+            parse("GET /"),
+            Err(errors::Errors::RequestLine("missing HTTP-version"))
+        );
+        for line in ["GET / HTTP/1.1 junk", "GET  / HTTP/1.1", "GET / HTTP/1.1 "] {
+            assert_eq!(
+                parse(line),
+                Err(errors::Errors::RequestLine(
+                    "expected method SP request-target SP HTTP-version"
+                )),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_parts_round_trip() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "POST / HTTP/1.1\r\nContent-Length: 4\r\nHere: here\r\n\r\nBODY"
+                .as_bytes()
+                .to_vec(),
+        );
         assert_eq!(res, Ok(()));
-        assert_eq!(r.body_complete(), true);
+        let dump = r.dump();
+
+        let (head, body) = r.into_parts();
+        assert_eq!(head.request_line, "POST / HTTP/1.1");
+        assert_eq!(head.headers.len(), 2);
+        assert_eq!(body, b"BODY".to_vec());
+
+        let r = Request::from_parts(head.clone(), body).unwrap();
+        assert!(r.body_complete());
+        assert_eq!(r.content_length, ContentLength::Value(4));
+        assert_eq!(r.dump(), dump);
+
+        assert_eq!(
+            Request::from_parts(head, b"TOO LONG".to_vec()).err(),
+            Some(errors::Errors::Header(
+                "Content-Length does not match the length of the body".to_owned()
+            ))
+        );
+
+        let mut headers = headers::Headers::default();
+        headers
+            .add("Transfer-Encoding".to_owned(), "chunked".to_owned())
+            .unwrap();
+        let head = RequestHead {
+            request_line: "POST / HTTP/1.1".to_owned(),
+            headers,
+        };
+        let r = Request::from_parts(head.clone(), b"2\r\nhi\r\n0\r\n\r\n".to_vec()).unwrap();
+        assert_eq!(r.body(), b"hi".to_vec());
+        for body in [&b""[..], b"2\r\nhi\r\n", b"2\r\nhi\r\n0\r\n"] {
+            assert_eq!(
+                Request::from_parts(head.clone(), body.to_vec()).err(),
+                Some(errors::Errors::Invalid(errors::ValidationIssue::Incomplete)),
+                "{:?}",
+                body
+            );
+        }
+        assert_eq!(
+            Request::from_parts(head, b"1\r\na\r\n0\r\n\r\nGARBAGE".to_vec()).err(),
+            Some(errors::Errors::Chunk("data after the last chunk"))
+        );
     }
-    */
 
+    #[cfg(feature = "serde_json")]
     #[test]
-    fn test_content_length() {
+    fn test_from_json() {
+        let mut headers = headers::Headers::default();
+        headers.add("Host".to_owned(), "x".to_owned()).unwrap();
+        headers
+            .add("Content-Length".to_owned(), "1".to_owned())
+            .unwrap();
+        let head = RequestHead {
+            request_line: "POST /items HTTP/1.1".to_owned(),
+            headers,
+        };
+        let value = std::collections::BTreeMap::from([("name", "a"), ("tag", "b")]);
+        let r = Request::from_json(head, &value).unwrap();
+        assert!(r.body_complete());
+        assert_eq!(r.body(), br#"{"name":"a","tag":"b"}"#.to_vec());
+        assert_eq!(
+            r.dump(),
+            b"POST /items HTTP/1.1\r\nHost: x\r\nContent-Type: application/json\r\nContent-Length: 22\r\n\r\n{\"name\":\"a\",\"tag\":\"b\"}"
+                .to_vec()
+        );
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn test_validate_json_body() {
+        #[derive(Debug, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+        struct Item {
+            #[schemars(length(min = 1))]
+            name: String,
+            #[schemars(range(max = 10))]
+            count: u8,
+        }
+        #[derive(Debug, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+        struct Contact {
+            #[schemars(regex(pattern = "^[a-z]+$"))]
+            name: String,
+            #[schemars(email)]
+            mail: String,
+        }
+        let request = |body: &str| {
+            let mut r = Request::default();
+            let raw = format!(
+                "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            assert_eq!(r.update_raw(&mut raw.into_bytes()), Ok(()));
+            r
+        };
+
+        let r = request(r#"{"name":"a","count":2}"#);
+        assert_eq!(
+            r.validate_json_body::<Item>(),
+            Ok(Item {
+                name: "a".to_owned(),
+                count: 2
+            })
+        );
+        // serde alone would accept these
+        let failed_at = |body: &str, path: &str| match request(body).validate_json_body::<Item>() {
+            Err(errors::Errors::JsonSchema(message)) => message.starts_with(path),
+            _ => false,
+        };
+        assert!(failed_at(r#"{"name":"","count":2}"#, "/name: "));
+        assert!(failed_at(r#"{"name":"a","count":11}"#, "/count: "));
+        // patterns and formats are checked too
+        assert!(request(r#"{"name":"ok","mail":"a@example.com"}"#)
+            .validate_json_body::<Contact>()
+            .is_ok());
+        assert!(matches!(
+            request(r#"{"name":"NOT LOWER 123","mail":"a@example.com"}"#)
+                .validate_json_body::<Contact>(),
+            Err(errors::Errors::JsonSchema(message)) if message.starts_with("/name: ")
+        ));
+        assert!(matches!(
+            request(r#"{"name":"ok","mail":"nope"}"#).validate_json_body::<Contact>(),
+            Err(errors::Errors::JsonSchema(message)) if message.starts_with("/mail: ")
+        ));
+        assert!(matches!(
+            request(r#"{"name":"a""#).validate_json_body::<Item>(),
+            Err(errors::Errors::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_parts_without_framing() {
+        let head = RequestHead {
+            request_line: "GET / HTTP/1.1".to_owned(),
+            headers: headers::Headers::default(),
+        };
+        let r = Request::from_parts(head.clone(), vec![]).unwrap();
+        assert!(r.body_complete());
+        assert_eq!(r.dump(), b"GET / HTTP/1.1\r\n\r\n".to_vec());
+
+        assert_eq!(
+            Request::from_parts(head, b"BODY".to_vec()).err(),
+            Some(errors::Errors::Header(
+                "a body requires either a Content-Length or a chunked Transfer-Encoding".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_dump_chunked() {
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 11\r\n\r\nhello world";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(
+            r.dump_chunked(0),
+            Err(errors::Errors::Chunk(
+                "chunk size must be greater than zero"
+            ))
+        );
+
+        let dump = r.dump_chunked(4).unwrap();
+        assert_eq!(
+            dump,
+            b"POST / HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nhell\r\n4\r\no wo\r\n3\r\nrld\r\n0\r\n\r\n"
+                .to_vec()
+        );
+        let mut chunked = Request::default();
+        assert_eq!(chunked.update_raw(&mut dump.clone()), Ok(()));
+        assert!(chunked.body_complete());
+        assert_eq!(chunked.body(), r.body());
+
+        assert!(chunked
+            .dump_chunked(16)
+            .unwrap()
+            .ends_with(b"b\r\nhello world\r\n0\r\n\r\n"));
+
+        // re-chunking keeps the trailers
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\nExpires: never\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.dump_chunked(16).unwrap(), raw.to_vec());
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"POST / HTTP/1.1\r\nContent-Length: 3\r\n\r\nab".to_vec()),
+            Ok(())
+        );
+        assert_eq!(r.dump_chunked(16), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_header_end_split_at_start() {
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"\r".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.headers_end, HeadersEnd::Scanning(0));
+
+        // two empty lines rather than an empty head, they come before the request line
+        let res = r.update_raw(&mut b"\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.headers_end, HeadersEnd::Scanning(0));
+        assert_eq!(r.skipped_empty_lines, 4);
+        assert!(!r.body_complete());
+
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.headers_end, HeadersEnd::FoundAt(14));
+        assert_eq!(r.raw_len(), 22);
+        assert!(r.body_complete());
+    }
+
+    #[test]
+    fn test_empty_request_line() {
+        for raw in [
+            &b"\r\nContent-Length: 2\r\n\r\nab"[..],
+            b"\r\nHost: x\r\n\r\n",
+        ] {
+            for mut r in [Request::default(), Request::strict()] {
+                assert!(
+                    matches!(
+                        r.update_raw(&mut raw.to_vec()),
+                        Err(errors::Errors::RequestLine(_))
+                    ),
+                    "{:?}",
+                    raw
+                );
+                assert!(!r.body_complete());
+            }
+        }
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b" \r\nHost: x\r\n\r\n".to_vec()),
+            Err(errors::Errors::RequestLine("missing method"))
+        );
+        let mut r = Request::lenient();
+        assert_eq!(
+            r.update_raw(&mut b"\n\r\nGET / HTTP/1.1\n\n".to_vec()),
+            Ok(())
+        );
+        assert_eq!(r.request_line, "GET / HTTP/1.1");
+    }
+
+    #[test]
+    fn test_small_fragments_from_empty() {
+        let mut r = Request::default();
+        assert_eq!(r.update_raw(&mut b"\r\n".to_vec()), Ok(()));
+        assert_eq!(r.headers_end, HeadersEnd::Scanning(0));
+
+        let raw = b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\nok";
+        for step in [1, 2] {
+            let mut r = Request::default();
+            for fragment in raw.chunks(step) {
+                assert_eq!(r.update_raw(&mut fragment.to_vec()), Ok(()));
+            }
+            assert_eq!(r.headers.len(), 2);
+            assert_eq!(r.body(), b"ok".to_vec());
+            assert!(r.body_complete());
+        }
+    }
+
+    #[test]
+    fn test_scan_is_incremental() {
+        let mut wire = b"GET / HTTP/1.1\r\n".to_vec();
+        for i in 0..100 {
+            wire.extend_from_slice(format!("X-Header-{}: {}\r\n", i, i).as_bytes());
+        }
+        wire.extend_from_slice(b"\r\n");
+
+        let mut r = Request::default();
+        for (i, byte) in wire.iter().enumerate() {
+            assert_eq!(r.update_raw(&mut vec![*byte]), Ok(()));
+            // only the bytes that could start a split HEADER_END are looked at again
+            if let HeadersEnd::Scanning(at) = r.headers_end {
+                assert!(r.raw.len() - at <= HEADER_END.len(), "{} {}", at, i);
+            }
+        }
+        assert_eq!(r.headers_end, HeadersEnd::FoundAt(wire.len() - 4));
+        assert_eq!(r.headers.len(), 100);
+        assert_eq!(r.headers.get("x-header-99").unwrap().value, "99");
+    }
+
+    #[test]
+    fn test_strict_host() {
+        let mut r = Request::strict();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\nAccept: */*\r\n\r\n".to_vec());
+        assert_eq!(
+            res,
+            Err(errors::Errors::Header("missing Host header".to_owned()))
+        );
+
+        let mut r = Request::strict();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\nHost: a\r\nhost: b\r\n\r\n".to_vec());
+        assert_eq!(
+            res,
+            Err(errors::Errors::Header("multiple Host headers".to_owned()))
+        );
+
+        let mut r = Request::strict();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\nHost: a\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.host(), Some(("a".to_owned(), None)));
+
+        let mut r = Request::strict();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\nHost: example.com:8080\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.host(), Some(("example.com".to_owned(), Some(8080))));
+
+        let mut r = Request::strict();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\nHost: [::1]:443\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.host(), Some(("::1".to_owned(), Some(443))));
+
+        // HTTP/1.0 did not require a Host header
+        let mut r = Request::strict();
+        let res = r.update_raw(&mut b"GET / HTTP/1.0\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.host(), None);
+
+        // and nothing is checked unless the request is strict
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let head = b"GET /chat HTTP/1.1\r\nHost: x\r\nConnection: keep-alive, Upgrade\r\nUpgrade: websocket\r\n\r\n";
+        let mut r = Request::default();
+        assert_eq!(r.update_raw(&mut head.to_vec()), Ok(()));
+        assert!(r.is_upgrade());
+        assert!(!r.body_complete());
+        assert!(r.needs_more_data());
+        assert_eq!(r.dump(), head.to_vec());
+
+        r.update_raw(&mut b"\x81\x05hello".to_vec()).unwrap();
+        assert!(!r.body_complete());
+        assert_eq!(r.dump().len(), head.len() + 7);
+
+        // both headers are required
+        let mut r = Request::default();
+        let raw = b"GET / HTTP/1.1\r\nUpgrade: websocket\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(!r.is_upgrade());
+        assert!(r.body_complete());
+
+        let mut r = Request::default();
+        let raw = b"GET / HTTP/1.1\r\nConnection: upgrade\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(!r.is_upgrade());
+    }
+
+    #[test]
+    fn test_byte_counters() {
+        let mut r = Request::default();
+        let fragments: [&[u8]; 3] = [
+            b"POST / HTTP/1.1\r\nContent-",
+            b"Length: 5\r\n\r\nab",
+            b"cde",
+        ];
+        assert_eq!(r.update_raw(&mut fragments[0].to_vec()), Ok(()));
+        assert_eq!((r.header_len(), r.body_len(), r.raw_len()), (None, 0, 25));
+        assert_eq!(r.update_raw(&mut fragments[1].to_vec()), Ok(()));
+        assert_eq!(
+            (r.header_len(), r.body_len(), r.raw_len()),
+            (Some(38), 2, 40)
+        );
+        assert_eq!(r.update_raw(&mut fragments[2].to_vec()), Ok(()));
+        assert_eq!(
+            (r.header_len(), r.body_len(), r.raw_len()),
+            (Some(38), 5, 43)
+        );
+
+        for options in [
+            ParseOptions::default(),
+            ParseOptions {
+                headers_only: true,
+                ..Default::default()
+            },
+        ] {
+            let mut r = Request {
+                options,
+                ..Default::default()
+            };
+            let fragments: [&[u8]; 3] = [
+                b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n",
+                b"5\r\nhel",
+                b"lo\r\n0\r\n\r\n",
+            ];
+            assert_eq!(r.update_raw(&mut fragments[0].to_vec()), Ok(()));
+            assert_eq!(
+                (r.header_len(), r.body_len(), r.raw_len()),
+                (Some(47), 0, 47)
+            );
+            assert_eq!(r.update_raw(&mut fragments[1].to_vec()), Ok(()));
+            assert_eq!(
+                (r.header_len(), r.body_len(), r.raw_len()),
+                (Some(47), 3, 53)
+            );
+            assert_eq!(r.update_raw(&mut fragments[2].to_vec()), Ok(()));
+            assert_eq!(
+                (r.header_len(), r.body_len(), r.raw_len()),
+                (Some(47), 5, 62)
+            );
+        }
+    }
+
+    #[test]
+    fn test_expected_len() {
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBODY";
+        assert_eq!(r.update_raw(&mut raw[..20].to_vec()), Ok(()));
+        assert_eq!(r.expected_len(), None);
+        assert_eq!(r.update_raw(&mut raw[20..38].to_vec()), Ok(()));
+        assert_eq!(r.expected_len(), Some(raw.len()));
+        assert_eq!(r.update_raw(&mut raw[38..].to_vec()), Ok(()));
+        assert_eq!(r.expected_len(), Some(raw.len()));
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"GET / HTTP/1.1\r\n\r\n".to_vec()),
+            Ok(())
+        );
+        assert_eq!(r.expected_len(), None);
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(
+                &mut b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1\r\n".to_vec()
+            ),
+            Ok(())
+        );
+        assert_eq!(r.expected_len(), None);
+    }
+
+    #[test]
+    fn test_excess_bytes() {
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBODY\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(r.body_complete());
+        assert_eq!(r.body(), b"BODY".to_vec());
+        assert_eq!(r.excess_bytes(), 2);
+        assert_eq!(r.dump(), raw[..raw.len() - 2].to_vec());
+
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBO";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.excess_bytes(), 0);
+        assert_eq!(r.update_raw(&mut b"DYGET / HTTP/1.1\r\n".to_vec()), Ok(()));
+        assert!(r.body_complete());
+        assert_eq!(r.body(), b"BODY".to_vec());
+        assert_eq!(r.excess_bytes(), 16);
+
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1\r\nB\r\n0\r\n\r\nGET";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.body(), b"B".to_vec());
+        assert_eq!(r.excess_bytes(), 3);
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"GET / HTTP/1.1\r\n\r\nGET".to_vec()),
+            Ok(())
+        );
+        assert_eq!(r.excess_bytes(), 3);
+    }
+
+    #[test]
+    fn test_effective_transfer_encodings() {
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(r.effective_transfer_encodings().is_empty());
+        assert_eq!(r.update_raw(&mut b"\r\n".to_vec()), Ok(()));
+        assert_eq!(r.effective_transfer_encodings(), vec!["gzip", "chunked"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut r = Request::default();
+        let raw = b"POST /a HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\nX-Sum: 1\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+
+        let json = serde_json::to_string(&r).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"request_line":"POST /a HTTP/1.1","#,
+                r#""headers":[{"key":"Host","value":"x"},{"key":"Transfer-Encoding","value":"chunked"}],"#,
+                r#""body":"Mg0KaGkNCjANClgtU3VtOiAxDQoNCg=="}"#
+            )
+        );
+
+        let back: Request = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+        assert_eq!(back.request_line, r.request_line);
+        assert_eq!(back.headers, r.headers);
+        assert_eq!(back.chunk_decoder, r.chunk_decoder);
+        assert_eq!(back.body(), b"hi".to_vec());
+        assert_eq!(back.trailers().len(), 1);
+        assert_eq!(back.dump(), raw.to_vec());
+        // every field is derived again by parsing
+        assert_eq!(format!("{:?}", back), format!("{:?}", r));
+
+        // a head that has not been completed is kept as the bytes received
+        let mut r = Request::default();
+        assert_eq!(r.update_raw(&mut b"GET / HTTP/1.1\r\nHo".to_vec()), Ok(()));
+        let back: Request = serde_json::from_str(&serde_json::to_string(&r).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", back), format!("{:?}", r));
+
+        let json = r#"{"request_line":"GET / HTTP/1.1","headers":[{"key":"X","value":"a\r\nEvil: b"}],"body":""}"#;
+        assert!(serde_json::from_str::<Request>(json).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_replay() {
+        let chunked =
+            b"POST /b HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n\x00\xff\r\r\n0\r\n\r\n";
+        let length = b"PUT /c HTTP/1.1\r\nContent-Length: 4\r\n\r\n\x89PNG";
+        for raw in [&chunked[..], &length[..]] {
+            let mut r = Request::default();
+            assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+
+            let json = serde_json::to_string(&r).unwrap();
+            let back: Request = serde_json::from_str(&json).unwrap();
+            assert!(back.body_complete());
+
+            let mut replayed = Request::default();
+            assert_eq!(replayed.update_raw(&mut back.dump()), Ok(()));
+            assert_eq!(replayed.request_line, r.request_line);
+            assert_eq!(replayed.headers, r.headers);
+            assert_eq!(replayed.body(), r.body());
+        }
+    }
+
+    #[test]
+    fn test_normalized_path() {
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET /a/../b HTTP/1.1\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.normalized_path(), Ok("/b".to_owned()));
+
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET /a/./b?x=/../y HTTP/1.1\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.normalized_path(), Ok("/a/b".to_owned()));
+
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET /../etc/passwd HTTP/1.1\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.normalized_path(), Ok("/etc/passwd".to_owned()));
+
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET /static//%2e%2e/%2E%2E/secret HTTP/1.1\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.normalized_path(), Ok("/secret".to_owned()));
+        assert_eq!(r.normalized_path_with(true), Ok("/secret".to_owned()));
+
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET //a//b HTTP/1.1\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.normalized_path(), Ok("//a//b".to_owned()));
+        assert_eq!(r.normalized_path_with(true), Ok("/a/b".to_owned()));
+
+        let r = Request::default();
+        assert_eq!(
+            r.normalized_path(),
+            Err(errors::Errors::RequestLine("missing request-target"))
+        );
+    }
+
+    #[test]
+    fn test_bare_line_feed() {
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\nHost: x\n\n".to_vec());
+        assert_eq!(res, Err(errors::Errors::BareLineFeed));
+
+        let mut r = Request::lenient();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\nHost: x\n\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.request_line, "GET / HTTP/1.1");
+        assert_eq!(r.headers.values[0].to_string(), "Host: x");
+        assert_eq!(r.headers.len(), 1);
+        assert!(r.body_complete());
+        assert_eq!(r.dump(), b"GET / HTTP/1.1\r\nHost: x\r\n\r\n".to_vec());
+    }
+
+    // bare CR and bare LF are already rejected while scanning for the end of the headers,
+    // as BareCarriageReturn and BareLineFeed respectively
+    #[test]
+    fn test_bare_newline_in_headers() {
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\nHost: x\r\n\r\n".to_vec());
+        assert_eq!(res, Err(errors::Errors::BareLineFeed));
+        assert_eq!(r.headers.len(), 0);
+
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\nHost: x\r".to_vec());
+        assert_eq!(res, Ok(()));
+        let res = r.update_raw(&mut b"X-Smuggled: y\r\n\r\n".to_vec());
+        assert_eq!(res, Err(errors::Errors::BareCarriageReturn));
+        assert_eq!(r.headers.len(), 0);
+    }
+
+    #[test]
+    fn test_lenient_mixed_line_endings() {
+        let mut r = Request::lenient();
+        let res = r.update_raw(&mut b"POST / HTTP/1.1\r\nContent-Length: 4\n".to_vec());
+        assert_eq!(res, Ok(()));
+        let res = r.update_raw(&mut b"Host: x\r\n\nBO\nY".to_vec());
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.headers.len(), 2);
+        assert_eq!(r.content_length, ContentLength::Value(4));
+        assert_eq!(r.body(), b"BO\nY".to_vec());
+        assert!(r.body_complete());
+    }
+
+    #[test]
+    fn test_bare_carriage_return() {
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\nHost: x\ry\r\n\r\n".to_vec());
+        assert_eq!(res, Err(errors::Errors::BareCarriageReturn));
+
+        let mut r = Request::lenient();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\nHost: x\ry\n\n".to_vec());
+        assert_eq!(res, Err(errors::Errors::BareCarriageReturn));
+
+        // a CR at the very end might still be followed by a LF
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r".to_vec());
+        assert_eq!(res, Ok(()));
+        let res = r.update_raw(&mut b"\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert!(r.body_complete());
+    }
+
+    #[test]
+    fn test_accept_encoding_weighted() {
         let mut r = Request::default();
         let res = r.update_raw(
-            &mut "POST / HTTP/1.1\r\nContent-Length: 4\r\nHere: here\r\n\r\nBODY"
+            &mut "GET / HTTP/1.1\r\nAccept-Encoding: gzip;q=0.8, br\r\naccept-encoding: deflate;q=0.8, identity;q=0\r\n\r\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(r.accept_encoding().len(), 4);
+
+        let weighted = r.accept_encoding_weighted();
+        let tiers = weighted
+            .iter()
+            .map(|(q, codings)| (q.0 .0, codings.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tiers,
+            vec![
+                (1.0, vec!["br".to_owned()]),
+                (0.8, vec!["gzip".to_owned(), "deflate".to_owned()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_accept_language() {
+        let mut r = Request::default();
+        let res = r.update_raw(
+            &mut "GET / HTTP/1.1\r\nAccept-Language: fr;q=0.5, en-US, de;q=0, *;q=0.1, en;q=0.5\r\n\r\n"
                 .as_bytes()
                 .to_vec(),
         );
-        assert_eq!(res, Ok(()));
-        assert_eq!(r.headers.values[0].to_string(), "Content-Length: 4");
-        assert_eq!(r.headers.values[1].to_string(), "Here: here");
-        assert_eq!(r.headers.values.len(), 2);
-        assert_eq!(r.content_length, ContentLength::Value(4));
-        assert_eq!(r.body(), vec![b'B', b'O', b'D', b'Y']);
-        assert_eq!(r.body_complete(), true);
+        assert_eq!(res, Ok(()));
+        assert_eq!(
+            r.accept_language(),
+            vec![
+                ("en-us".to_owned(), 1.0),
+                ("fr".to_owned(), 0.5),
+                ("en".to_owned(), 0.5),
+                ("*".to_owned(), 0.1),
+                ("de".to_owned(), 0.0),
+            ]
+        );
+
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\n\r\n".to_vec());
+        assert_eq!(res, Ok(()));
+        assert!(r.accept_language().is_empty());
+    }
+
+    #[test]
+    fn test_multipart_boundary() {
+        let cases = [
+            ("multipart/form-data; boundary=abc", true, Some("abc")),
+            (
+                "Multipart/Mixed;charset=utf-8; BOUNDARY=\"a b:c\"",
+                true,
+                Some("a b:c"),
+            ),
+            ("multipart/form-data", true, None),
+            ("multipart/form-data; boundary=\"\"", true, None),
+            ("text/plain; boundary=abc", false, None),
+            ("multi", false, None),
+        ];
+        for (content_type, multipart, boundary) in cases {
+            let mut r = Request::default();
+            let raw = format!("POST / HTTP/1.1\r\nContent-Type: {}\r\n\r\n", content_type);
+            assert_eq!(r.update_raw(&mut raw.into_bytes()), Ok(()));
+            assert_eq!(r.is_multipart(), multipart, "{}", content_type);
+            assert_eq!(r.boundary(), boundary, "{}", content_type);
+        }
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"GET / HTTP/1.1\r\n\r\n".to_vec()),
+            Ok(())
+        );
+        assert!(!r.is_multipart());
     }
 
     #[test]
-    fn test_body_incomplete() {
+    fn test_upgrade_insecure_requests() {
         let mut r = Request::default();
         let res = r.update_raw(
-            &mut "POST / HTTP/1.1\r\nContent-Length: 5\r\nHere: here\r\n\r\nBODY"
+            &mut "GET / HTTP/1.1\r\nHost: x\r\nUpgrade-Insecure-Requests: 1\r\n\r\n"
                 .as_bytes()
                 .to_vec(),
         );
         assert_eq!(res, Ok(()));
-        assert_eq!(r.headers.values[0].to_string(), "Content-Length: 5");
-        assert_eq!(r.headers.values[1].to_string(), "Here: here");
-        assert_eq!(r.headers.values.len(), 2);
-        assert_eq!(r.content_length, ContentLength::Value(5));
-        assert_eq!(r.body_complete(), false);
+        assert!(r.upgrade_insecure_requests());
 
-        let res = r.update_raw(&mut "S".as_bytes().to_vec());
+        let mut r = Request::default();
+        let res = r.update_raw(&mut "GET / HTTP/1.1\r\nHost: x\r\n\r\n".as_bytes().to_vec());
         assert_eq!(res, Ok(()));
-        assert_eq!(r.body_complete(), true);
+        assert!(!r.upgrade_insecure_requests());
     }
 
     #[test]
-    fn test_content_length_zero() {
+    fn test_dnssec_status() {
+        let cases = [
+            ("GET / HTTP/1.1\r\nDNSSEC: ok\r\n\r\n", Some(true)),
+            ("GET / HTTP/1.1\r\ndnssec: Insecure \r\n\r\n", Some(false)),
+            ("GET / HTTP/1.1\r\nDNSSEC: bogus\r\n\r\n", None),
+            ("GET / HTTP/1.1\r\n\r\n", None),
+        ];
+        for (raw, expected) in cases {
+            let mut r = Request::default();
+            assert_eq!(r.update_raw(&mut raw.as_bytes().to_vec()), Ok(()));
+            assert_eq!(r.dnssec_status(), expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let cases = [
+            ("GET / HTTP/1.1\r\n\r\n", true),
+            ("GET / HTTP/1.1\r\nConnection: Close\r\n\r\n", false),
+            ("GET / HTTP/1.0\r\n\r\n", false),
+            ("GET / HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n", true),
+            (
+                "GET / HTTP/1.1\r\nConnection: close, keep-alive\r\n\r\n",
+                false,
+            ),
+            (
+                "GET / HTTP/1.0\r\nConnection: keep-alive, close\r\n\r\n",
+                false,
+            ),
+            (
+                "GET / HTTP/1.1\r\nConnection: upgrade\r\nConnection: close\r\n\r\n",
+                false,
+            ),
+            ("GET / HTTP/1.1\r\nConnection: closed\r\n\r\n", true),
+            ("GET / HTTP/1.1\r\nconnection: TE , CLOSE\r\n\r\n", false),
+            ("GET / HTTP/1.0\r\nCONNECTION:keep-alive\r\n\r\n", true),
+        ];
+        for (wire, keep_alive) in cases {
+            let mut r = Request::default();
+            let res = r.update_raw(&mut wire.as_bytes().to_vec());
+            assert_eq!(res, Ok(()));
+            assert_eq!(r.keep_alive(), keep_alive, "{}", wire);
+        }
+    }
+
+    #[test]
+    fn test_route() {
         let mut r = Request::default();
-        let res = r.update_raw(&mut "GET / HTTP/1.1\r\nHere: here\r\n".as_bytes().to_vec());
+        let res = r.update_raw(&mut b"POST /users/42?x=1 HTTP/1.1\r\nHost: x\r\n\r\n".to_vec());
         assert_eq!(res, Ok(()));
-        assert_eq!(r.body_complete(), false);
+        assert_eq!(
+            r.route(),
+            Ok((method::Method::Post, "/users/42".to_owned()))
+        );
 
-        let res = r.update_raw(&mut "More: more\r\nFinal: final\r\n\r\n".as_bytes().to_vec());
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET /a/%2E%2E/b%20c HTTP/1.1\r\n\r\n".to_vec());
         assert_eq!(res, Ok(()));
-        assert_eq!(r.headers.values[0].to_string(), "Here: here");
-        assert_eq!(r.headers.values[1].to_string(), "More: more");
-        assert_eq!(r.headers.values[2].to_string(), "Final: final");
-        assert_eq!(r.headers.values.len(), 3);
-        assert_eq!(r.content_length, ContentLength::Unset);
-        assert_eq!(r.body_complete(), true);
+        assert_eq!(r.route(), Ok((method::Method::Get, "/b c".to_owned())));
+
+        let r = Request::default();
+        assert_eq!(
+            r.route(),
+            Err(errors::Errors::RequestLine("invalid method"))
+        );
     }
 
     #[test]
-    fn test_multi_line_header() {
+    fn test_expect() {
+        let raw =
+            b"POST / HTTP/1.1\r\nHost: x\r\nExpect: 100-Continue \r\nContent-Length: 1\r\n\r\n";
+        let mut r = Request::strict();
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(r.is_expect_header_present());
+        assert_eq!(r.expect_header_value(), Some("100-Continue"));
+        assert!(r.expect_continue());
+        assert!(r.has_full_headers());
+        assert!(!r.body_complete());
+
+        // OWS on either side is not part of the value
+        let raw = b"POST / HTTP/1.1\r\nExpect:\t 100-CONTINUE\t\r\nContent-Length: 1\r\n\r\n";
         let mut r = Request::default();
-        let res = r.update_raw(
-            &mut "GET / HTTP/1.1\r\nFirst: wrapp\r\n   ing\r\n\ttest\r\nSecond: wrapp\r\n    ing\r\n\ttest\r\n\r\n"
-                .as_bytes()
-                .to_vec(),
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(r.expect_continue());
+
+        let raw = b"POST / HTTP/1.1\r\nExpect: 200-ok\r\n\r\n";
+        let mut r = Request::default();
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.expect_header_value(), Some("200-ok"));
+        assert!(!r.expect_continue());
+
+        let mut r = Request::strict();
+        assert_eq!(
+            r.update_raw(&mut raw.to_vec()),
+            Err(errors::Errors::UnknownExpectValue("200-ok".to_owned()))
         );
-        assert_eq!(res, Ok(()));
-        assert_eq!(r.headers.values[0].to_string(), "First: wrappingtest");
-        assert_eq!(r.headers.values[1].to_string(), "Second: wrappingtest");
-        assert_eq!(r.body_complete(), true);
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"GET / HTTP/1.1\r\n\r\n".to_vec()),
+            Ok(())
+        );
+        assert!(!r.is_expect_header_present());
+        assert!(!r.expect_continue());
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"POST / HTTP/1.1\r\nExpect: 100-continue\r\n".to_vec()),
+            Ok(())
+        );
+        // headers are only parsed once the head is complete
+        assert!(!r.has_full_headers());
+        assert!(!r.expect_continue());
     }
 
     #[test]
-    fn test_bad_chunked_header() {
+    fn test_parse_progress() {
+        let progress = |stage, bytes_in_stage| ParseProgress {
+            stage,
+            bytes_in_stage,
+        };
+
         let mut r = Request::default();
-        let res = r.update_raw(
-            &mut "GET / HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n"
-                .as_bytes()
-                .to_vec(),
+        assert_eq!(r.parse_progress(), progress(Stage::RequestLine, 0));
+        r.update_raw(&mut b"POST / HT".to_vec()).unwrap();
+        assert_eq!(r.parse_progress(), progress(Stage::RequestLine, 9));
+        r.update_raw(&mut b"TP/1.1\r\n".to_vec()).unwrap();
+        assert_eq!(r.parse_progress(), progress(Stage::Headers, 0));
+        r.update_raw(&mut b"Content-Length: 4\r\n\r".to_vec())
+            .unwrap();
+        assert_eq!(r.parse_progress(), progress(Stage::Headers, 20));
+        r.update_raw(&mut b"\n".to_vec()).unwrap();
+        assert_eq!(r.parse_progress(), progress(Stage::Body, 0));
+        r.update_raw(&mut b"abc".to_vec()).unwrap();
+        assert_eq!(r.parse_progress(), progress(Stage::Body, 3));
+    }
+
+    #[test]
+    fn test_target_components() {
+        let mut r = Request::default();
+        let raw = b"GET /a+b/c%20d?q=rust+lang&tag=a&tag=b%26c&empty HTTP/1.1\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.decoded_path(), Ok("/a+b/c d".to_owned()));
+        assert_eq!(r.authority(), None);
+        assert_eq!(
+            r.query(),
+            Ok(vec![
+                ("q".to_owned(), "rust lang".to_owned()),
+                ("tag".to_owned(), "a".to_owned()),
+                ("tag".to_owned(), "b&c".to_owned()),
+                ("empty".to_owned(), "".to_owned()),
+            ])
         );
+        assert_eq!(r.query_get("tag"), Ok(Some("a".to_owned())));
+        assert_eq!(r.query_get("missing"), Ok(None));
+
+        let mut r = Request::default();
+        let raw = b"GET http://example.com:8080/p%41th?x=1 HTTP/1.1\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.authority(), Some("example.com:8080"));
+        assert_eq!(r.decoded_path(), Ok("/pAth".to_owned()));
+        assert_eq!(r.query_get("x"), Ok(Some("1".to_owned())));
+
+        let mut r = Request::default();
+        let raw = b"GET /bad%G1?x=%2 HTTP/1.1\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.decoded_path(), Err(errors::Errors::PercentDecode));
+        assert_eq!(r.query(), Err(errors::Errors::PercentDecode));
+    }
+
+    #[test]
+    fn test_method_and_path() {
+        let mut r = Request::default();
         assert_eq!(
-            res,
-            Err(errors::Errors::Header(
-                "chunked must appear at the very end of the Transfer-Encoding header value",
-            ))
+            r.update_raw(&mut b"POST /a%20b?x=1 HTTP/1.1\r\nHost: x".to_vec()),
+            Ok(())
         );
+        assert_eq!(r.method(), None);
+        assert_eq!(r.path(), None);
+
+        assert_eq!(r.update_raw(&mut b"\r\n\r\n".to_vec()), Ok(()));
+        assert_eq!(r.method(), Some("POST"));
+        assert_eq!(r.path(), Some("/a%20b"));
+
+        let mut r = Request::default();
+        let raw = b"GET http://example.com HTTP/1.1\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.method(), Some("GET"));
+        assert_eq!(r.path(), Some("/"));
     }
 
     #[test]
-    fn test_mutually_exclusive() {
+    fn test_decoded_path() {
+        let decoded_path = |target: &str| {
+            let mut r = Request::default();
+            let raw = format!("GET {} HTTP/1.1\r\n\r\n", target);
+            assert_eq!(r.update_raw(&mut raw.into_bytes()), Ok(()));
+            r.decoded_path()
+        };
+        assert_eq!(decoded_path("/a%20b"), Ok("/a b".to_owned()));
+        assert_eq!(decoded_path("/caf%C3%A9?x=%C3"), Ok("/café".to_owned()));
+        assert_eq!(decoded_path("/bad%2"), Err(errors::Errors::PercentDecode));
+        assert_eq!(decoded_path("/bad%"), Err(errors::Errors::PercentDecode));
+        assert!(matches!(
+            decoded_path("/caf%E9"),
+            Err(errors::Errors::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_size_class() {
+        let size_class = |head: &str, body: &str, options: ParseOptions| {
+            let mut r = Request {
+                options,
+                ..Default::default()
+            };
+            let raw = format!("POST / HTTP/1.1\r\n{}\r\n{}", head, body);
+            assert_eq!(r.update_raw(&mut raw.into_bytes()), Ok(()));
+            r.size_class()
+        };
+        let cases = [
+            ("", "", SizeClass::Empty),
+            ("Content-Length: 0\r\n", "", SizeClass::Empty),
+            // declared, before any of the body has arrived
+            ("Content-Length: 1024\r\n", "", SizeClass::Small),
+            ("Content-Length: 1025\r\n", "", SizeClass::Medium),
+            ("Content-Length: 1048576\r\n", "", SizeClass::Large),
+            ("Content-Length: 1048577\r\n", "", SizeClass::ExtraLarge),
+            ("Transfer-Encoding: chunked\r\n", "", SizeClass::Empty),
+            (
+                "Transfer-Encoding: chunked\r\n",
+                "3\r\nabc\r\n",
+                SizeClass::Small,
+            ),
+        ];
+        for (head, body, class) in cases {
+            assert_eq!(
+                size_class(head, body, ParseOptions::default()),
+                class,
+                "{}",
+                head
+            );
+        }
+
+        let options = ParseOptions {
+            size_class_thresholds: SizeClassThresholds {
+                small: 2,
+                medium: 4,
+                large: 8,
+            },
+            ..Default::default()
+        };
+        let head = "Transfer-Encoding: chunked\r\n";
+        assert_eq!(
+            size_class(head, "3\r\nabc\r\n", options.clone()),
+            SizeClass::Medium
+        );
+        assert_eq!(
+            size_class(head, "9\r\nabcdefghi\r\n0\r\n\r\n", options),
+            SizeClass::ExtraLarge
+        );
+    }
+
+    #[test]
+    fn test_query_string() {
+        let query_string = |target: &str| {
+            let mut r = Request::default();
+            let raw = format!("GET {} HTTP/1.1\r\n\r\n", target);
+            assert_eq!(r.update_raw(&mut raw.into_bytes()), Ok(()));
+            r.query_string().map(|q| q.to_owned())
+        };
+        assert_eq!(
+            query_string("/a%20b?q=x%20y&z=1"),
+            Some("q=x%20y&z=1".to_owned())
+        );
+        assert_eq!(
+            query_string("http://example.com/p?a=b"),
+            Some("a=b".to_owned())
+        );
+        assert_eq!(query_string("/p?"), Some("".to_owned()));
+        assert_eq!(query_string("/p"), None);
+        assert_eq!(Request::default().query_string(), None);
+    }
+
+    #[test]
+    fn test_query_pairs() {
+        let query_pairs = |target: &str| {
+            let mut r = Request::default();
+            let raw = format!("GET {} HTTP/1.1\r\n\r\n", target);
+            assert_eq!(r.update_raw(&mut raw.into_bytes()), Ok(()));
+            r.query_pairs()
+        };
+        assert_eq!(
+            query_pairs("/s?q=rust+lang&page=2&page=3&flag"),
+            vec![
+                ("q".to_owned(), "rust lang".to_owned()),
+                ("page".to_owned(), "2".to_owned()),
+                ("page".to_owned(), "3".to_owned()),
+                ("flag".to_owned(), "".to_owned()),
+            ]
+        );
+        assert_eq!(
+            query_pairs("/s?a=b=c&bad=%2&x=%41"),
+            vec![
+                ("a".to_owned(), "b=c".to_owned()),
+                ("x".to_owned(), "A".to_owned()),
+            ]
+        );
+        assert!(query_pairs("/s?").is_empty());
+        assert!(query_pairs("/s").is_empty());
+
         let mut r = Request::default();
-        let res = r.update_raw(
-            &mut "GET / HTTP/1.1\r\nContent-Length: 10\r\nTransfer-Encoding: chunked\r\n\r\n"
-                .as_bytes()
-                .to_vec(),
+        let raw = b"GET /s?q=a%20b+c&bad=%2 HTTP/1.1\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(
+            r.decoded_query_pairs(),
+            vec![("q".to_owned(), "a b c".to_owned())]
         );
+        assert_eq!(r.decoded_query_pairs(), r.query_pairs());
+    }
+
+    #[test]
+    fn test_validate_request() {
+        let mut r = Request::default();
+        let raw = b"GET / HTTP/1.1\r\nHost: a\r\nAccept: a\r\naccept: b\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.validate_request(), Ok(()));
+
+        let mut r = Request::default();
+        let raw =
+            b"GET / HTTP/1.1\r\nHost: a\r\nAuthorization: x\r\nhost: b\r\nAuthorization: y\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
         assert_eq!(
-            res,
-            Err(errors::Errors::Header(
-                "Transfer-Encoding and Content-Length headers are mutually exclusive",
-            ))
+            r.validate_request(),
+            Err(vec![
+                errors::ValidationIssue::DuplicateHeader("host".to_owned()),
+                errors::ValidationIssue::DuplicateHeader("authorization".to_owned()),
+            ])
         );
     }
 
     #[test]
-    fn test_body() {
+    fn test_declared_trailer_names() {
         let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nTrailer: X-Sum, Expires\r\ntrailer: ,x-Other\r\nTransfer-Encoding: chunked\r\n\r\n0\r\nX-Sum: 1\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(
+            r.declared_trailer_names(),
+            vec!["x-sum", "expires", "x-other"]
+        );
+        assert!(r.trailers().has("x-sum"));
 
-        let res = r.update_raw(&mut "POST TEST\r".as_bytes().to_vec());
-        assert_eq!(res, Ok(()));
-        let res = r.update_raw(&mut "\nContent-L".as_bytes().to_vec());
-        assert_eq!(res, Ok(()));
-        let res = r.update_raw(&mut "ength: 4\r\n".as_bytes().to_vec());
-        assert_eq!(res, Ok(()));
-        let res = r.update_raw(&mut "\r\nBODY".as_bytes().to_vec());
-        assert_eq!(res, Ok(()));
+        let mut r = Request::default();
+        assert_eq!(
+            r.update_raw(&mut b"GET / HTTP/1.1\r\n\r\n".to_vec()),
+            Ok(())
+        );
+        assert!(r.declared_trailer_names().is_empty());
+    }
 
-        assert_eq!(r.headers.values[0].to_string(), "Content-Length: 4");
-        assert_eq!(r.body_complete(), true);
+    #[test]
+    fn test_feed() {
+        let wire = b"POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\nhiGET /next HTTP/1.1\r\n";
+        let mut r = Request::default();
+        assert_eq!(r.feed(&wire[..10]), Ok(10));
+        assert_eq!(r.feed(&wire[10..]), Ok(30));
+        assert!(r.body_complete());
+        assert_eq!(r.body(), b"hi".to_vec());
+        assert_eq!(r.excess_bytes(), 0);
+        assert_eq!(r.dump(), wire[..40].to_vec());
+        // nothing more is taken once the request is complete
+        assert_eq!(r.feed(&wire[40..]), Ok(0));
+
+        let wire = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\nrest";
+        let mut r = Request::default();
+        assert_eq!(r.feed(wire), Ok(wire.len() - 4));
+        assert_eq!(r.body(), b"hi".to_vec());
+
+        let mut r = Request {
+            options: ParseOptions {
+                headers_only: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(r.feed(wire), Ok(wire.len() - 4));
+        assert!(r.body_complete());
+
+        // the CRs added to bare LFs were never part of data
+        let wire = b"GET / HTTP/1.1\nHost: x\n\nGET /next HTTP/1.1\nHost: x\n\n";
+        let mut r = Request::lenient();
+        assert_eq!(r.feed(&wire[..24]), Ok(24));
+        let mut r = Request::lenient();
+        assert_eq!(r.feed(wire), Ok(24));
+        let mut r = Request::lenient();
+        assert_eq!(r.feed(&wire[..10]), Ok(10));
+        assert_eq!(r.feed(&wire[10..]), Ok(14));
+        assert!(r.body_complete());
+        assert_eq!(r.inserted_carriage_returns, 3);
+        assert_eq!(r.raw_len(), 24);
+        assert_eq!(r.consumed_len(), 27);
+        let mut next = Request::lenient();
+        assert_eq!(next.feed(&wire[24..]), Ok(wire.len() - 24));
+        assert_eq!(next.request_line, "GET /next HTTP/1.1");
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut r = Request::with_capacity(4, 1024);
+        assert!(r.raw.capacity() >= 1024);
+        assert!(r.headers.values.capacity() >= 4);
+
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\nhi";
+        let before = r.raw.as_ptr();
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        // appended into the buffer allocated up front
+        assert_eq!(r.raw.as_ptr(), before);
+        assert_eq!(r.body(), b"hi".to_vec());
+    }
+
+    #[test]
+    fn test_is_complete_and_valid() {
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 2\r\n\r\nh";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(
+            r.is_complete_and_valid(),
+            Err(vec![errors::ValidationIssue::Incomplete])
+        );
+        assert_eq!(r.update_raw(&mut b"i".to_vec()), Ok(()));
+        assert_eq!(r.is_complete_and_valid(), Ok(()));
+
+        let mut r = Request::default();
+        let raw = b"GET / HTTP/1.1\r\nHost: a\r\nhost: b\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(
+            r.is_complete_and_valid(),
+            Err(vec![errors::ValidationIssue::DuplicateHeader(
+                "host".to_owned()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_is_valid_http11_request() {
+        let valid = |raw: &[u8]| {
+            let mut r = Request::default();
+            let _ = r.update_raw(&mut raw.to_vec());
+            r.is_valid_http11_request()
+        };
+        assert!(valid(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n"));
+        assert!(valid(
+            b"POST / HTTP/1.1\r\nHost: x\r\nExpect: 100-continue\r\nContent-Length: 2\r\n\r\nhi"
+        ));
+        assert!(valid(b"TRACE / HTTP/1.1\r\nHost: x\r\n\r\n"));
+
+        // incomplete
+        assert!(!valid(
+            b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\nh"
+        ));
+        // host
+        assert!(!valid(b"GET / HTTP/1.1\r\n\r\n"));
+        assert!(!valid(b"GET / HTTP/1.1\r\nHost: x\r\nHost: y\r\n\r\n"));
+        // framing
+        assert!(!valid(
+            b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 1\r\nTransfer-Encoding: chunked\r\n\r\n"
+        ));
+        // request line
+        assert!(!valid(b"G(T / HTTP/1.1\r\nHost: x\r\n\r\n"));
+        assert!(!valid(b"GET HTTP/1.1\r\nHost: x\r\n\r\n"));
+        assert!(!valid(b"GET / HTTP/1.0\r\nHost: x\r\n\r\n"));
+        // header names
+        assert!(!valid(b"GET / HTTP/1.1\r\nHost: x\r\nX(y): z\r\n\r\n"));
+        // TRACE with a body
+        assert!(!valid(
+            b"TRACE / HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\nhi"
+        ));
+        // expect
+        assert!(!valid(
+            b"GET / HTTP/1.1\r\nHost: x\r\nExpect: 200-ok\r\n\r\n"
+        ));
+    }
+
+    #[test]
+    fn test_header_eq_constant_time() {
+        let mut r = Request::default();
+        let raw = b"GET / HTTP/1.1\r\nAuthorization: Bearer s3cret \r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(r.header_eq_constant_time("authorization", b"Bearer s3cret"));
+        assert!(!r.header_eq_constant_time("authorization", b"Bearer s3creT"));
+        assert!(!r.header_eq_constant_time("authorization", b"Bearer s3cret2"));
+        assert!(!r.header_eq_constant_time("authorization", b"Bearer"));
+        assert!(!r.header_eq_constant_time("authorization", b""));
+        assert!(!r.header_eq_constant_time("x-api-key", b"Bearer s3cret"));
+    }
+
+    #[test]
+    fn test_state() {
+        let mut r = Request::default();
+        assert_eq!(r.state(), ParseState::AwaitingHeaders);
+        assert!(r.needs_more_data());
+        r.update_raw(&mut b"GET / HTTP/1.1\r\nHost: x\r\n".to_vec())
+            .unwrap();
+        assert_eq!(r.state(), ParseState::AwaitingHeaders);
+        r.update_raw(&mut b"\r\n".to_vec()).unwrap();
+        assert_eq!(r.state(), ParseState::Complete);
+        assert!(!r.needs_more_data());
+
+        // without Content-Length or Transfer-Encoding there is no body, even for a POST
+        let mut r = Request::default();
+        r.update_raw(&mut b"POST / HTTP/1.1\r\n\r\nGET / HTTP/1.1".to_vec())
+            .unwrap();
+        assert_eq!(r.state(), ParseState::Complete);
+        assert_eq!(r.body(), b"".to_vec());
+
+        let mut r = Request::default();
+        r.update_raw(&mut b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nab".to_vec())
+            .unwrap();
+        assert_eq!(
+            r.state(),
+            ParseState::AwaitingBody {
+                received: 2,
+                expected: Some(5)
+            }
+        );
+        r.update_raw(&mut b"cde".to_vec()).unwrap();
+        assert_eq!(r.state(), ParseState::Complete);
+
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n";
+        r.update_raw(&mut raw.to_vec()).unwrap();
+        assert_eq!(
+            r.state(),
+            ParseState::AwaitingBody {
+                received: 3,
+                expected: None
+            }
+        );
+        r.update_raw(&mut b"0\r\n\r\n".to_vec()).unwrap();
+        assert_eq!(r.state(), ParseState::Complete);
+
+        // a client waiting for a 100 (Continue)
+        let mut r = Request::default();
+        let raw = b"PUT / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\n";
+        r.update_raw(&mut raw.to_vec()).unwrap();
+        assert!(r.expect_continue());
+        assert!(!r.body_complete());
+        assert!(r.needs_more_data());
+        assert_eq!(
+            r.state(),
+            ParseState::AwaitingContinue { expected: Some(3) }
+        );
+        assert_eq!(
+            r.debug_trace(),
+            "Request[state=AwaitingContinue, chunked=false]"
+        );
+        r.update_raw(&mut b"a".to_vec()).unwrap();
+        assert_eq!(
+            r.state(),
+            ParseState::AwaitingBody {
+                received: 1,
+                expected: Some(3)
+            }
+        );
+        r.update_raw(&mut b"bc".to_vec()).unwrap();
+        assert_eq!(r.state(), ParseState::Complete);
+        assert_eq!(r.body(), b"abc".to_vec());
+
+        let mut r = Request::default();
+        let res = r.update_raw(&mut b"GET / HTTP/1.1\r\nfo o: x\r\n\r\n".to_vec());
+        assert_eq!(res, Err(errors::Errors::HeaderKeyWhitespace));
+        assert_eq!(r.state(), ParseState::Failed);
+        assert!(!r.needs_more_data());
+        assert_eq!(
+            r.update_raw(&mut b"more".to_vec()),
+            Err(errors::Errors::HeaderKeyWhitespace)
+        );
+    }
+
+    #[test]
+    fn test_headers_only() {
+        let headers_only = || Request {
+            options: ParseOptions {
+                headers_only: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let size = 10 * 1024 * 1024;
+        let head = format!("POST /upload HTTP/1.1\r\nContent-Length: {}\r\n\r\n", size);
+        let mut stream = head.as_bytes().to_vec();
+        stream.resize(stream.len() + size, b'x');
+        stream.extend_from_slice(b"GET /next HTTP/1.1\r\n\r\n");
+
+        let mut r = headers_only();
+        let mut fed = 0;
+        for fragment in stream.chunks(64 * 1024) {
+            r.update_raw(&mut fragment.to_vec()).unwrap();
+            fed += fragment.len();
+            assert!(r.raw.capacity() <= 128 * 1024);
+            if r.body_complete() {
+                break;
+            }
+        }
+        assert!(r.body_complete());
+        assert_eq!(r.raw.len(), head.len());
+        assert_eq!(r.discarded_body_bytes, size);
+        assert_eq!(r.consumed_len(), head.len() + size);
+        assert_eq!(r.body(), b"".to_vec());
+        assert_eq!(r.dump(), head.as_bytes().to_vec());
+
+        // whatever was fed past consumed_len is the start of the next request
+        let mut next = headers_only();
+        let mut rest = stream[r.consumed_len()..fed].to_vec();
+        assert_eq!(next.update_raw(&mut rest), Ok(()));
+        assert_eq!(next.request_line, "GET /next HTTP/1.1");
+        assert!(next.body_complete());
+
+        let mut r = headers_only();
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\nGET";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert!(r.body_complete());
+        assert_eq!(r.discarded_body_bytes, 13);
+        assert_eq!(r.consumed_len(), raw.len() - 3);
+        assert_eq!(r.body(), b"".to_vec());
+
+        // progress still counts the decoded bytes that were not kept
+        let mut r = headers_only();
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(
+            r.state(),
+            ParseState::AwaitingBody {
+                received: 5,
+                expected: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_debug_trace() {
+        let mut r = Request::default();
+        r.update_raw(&mut b"POST / HTTP/1.1\r\nContent-Length: 500\r\n".to_vec())
+            .unwrap();
+        assert_eq!(
+            r.debug_trace(),
+            "Request[state=ScanningHeaders at=34, raw=38 bytes]"
+        );
+        r.update_raw(&mut [b"\r\n".to_vec(), vec![b'x'; 100]].concat())
+            .unwrap();
+        assert_eq!(
+            r.debug_trace(),
+            "Request[state=BodyIncomplete 100/500 bytes, chunked=false]"
+        );
+
+        let mut r = Request::default();
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n";
+        r.update_raw(&mut raw.to_vec()).unwrap();
+        assert_eq!(
+            r.debug_trace(),
+            "Request[state=BodyIncomplete 2 bytes, chunked=true]"
+        );
+        r.update_raw(&mut b"0\r\n\r\n".to_vec()).unwrap();
+        assert_eq!(
+            r.debug_trace(),
+            "Request[state=Complete \"POST / HTTP/1.1\", headers=1, body=2 bytes, chunked=true]"
+        );
+
+        let mut r = Request::default();
+        let _ = r.update_raw(&mut b"GET / HTTP/1.1\nHost: x".to_vec());
+        assert_eq!(
+            r.debug_trace(),
+            "Request[state=Failed \"line feed without a preceding carriage return\", raw=22 bytes]"
+        );
+    }
+
+    #[test]
+    fn test_body_complete_states() {
+        let head = b"POST / HTTP/1.1\r\n\r\n";
+        let request = |headers_end, is_chunked, content_length| Request {
+            raw: [head.to_vec(), b"ab".to_vec()].concat(),
+            headers_end,
+            is_chunked,
+            content_length,
+            ..Default::default()
+        };
+        let found = HeadersEnd::FoundAt(head.len() - HEADER_END.len());
+
+        for headers_end in [HeadersEnd::Unset, HeadersEnd::Scanning(3)] {
+            for is_chunked in [Chunked::Unset, Chunked::Processing, Chunked::Complete] {
+                for content_length in [ContentLength::Unset, ContentLength::Value(2)] {
+                    let r = request(headers_end.clone(), is_chunked.clone(), content_length);
+                    assert!(!r.body_complete());
+                }
+            }
+        }
+        for content_length in [
+            ContentLength::Unset,
+            ContentLength::Value(2),
+            ContentLength::Value(3),
+        ] {
+            let r = request(found.clone(), Chunked::Complete, content_length.clone());
+            assert!(r.body_complete());
+            let r = request(found.clone(), Chunked::Processing, content_length);
+            assert!(!r.body_complete());
+        }
+        assert!(request(found.clone(), Chunked::Unset, ContentLength::Unset).body_complete());
+        assert!(request(found.clone(), Chunked::Unset, ContentLength::Value(2)).body_complete());
+        assert!(!request(found, Chunked::Unset, ContentLength::Value(3)).body_complete());
+    }
+
+    #[test]
+    fn test_folded_header_cannot_smuggle() {
+        let mut r = Request::default();
+        let raw = b"GET / HTTP/1.1\r\nX-Name: a\r\n Evil: yes\r\n\r\n";
+        assert_eq!(r.update_raw(&mut raw.to_vec()), Ok(()));
+        assert_eq!(r.headers.len(), 1);
+        assert!(r.headers.get("evil").is_none());
+        assert!(!r.dump().windows(6).any(|w| w == b"\nEvil:"));
     }
 
     #[test]
@@ -439,10 +3906,14 @@ mod tests {
                 .to_vec(),
         );
         assert_eq!(res, Ok(()));
-        assert_eq!(r.headers.values[0].to_string(), "Wrapping: pre-update");
+        assert_eq!(r.headers.values[0].to_string(), "Wrapping: pre - update");
+        assert_eq!(
+            r.headers.get_raw_bytes("wrapping"),
+            Some(&b"Wrapping: pre - update"[..])
+        );
         assert_eq!(r.headers.values[1].to_string(), "Another: header");
         assert_eq!(r.headers.values[2].to_string(), "Content-Length: 7");
-        assert_eq!(r.body_complete(), true);
+        assert!(r.body_complete());
 
         let res = match String::from_utf8(r.dump()) {
             Ok(s) => s,
@@ -450,7 +3921,7 @@ mod tests {
         };
         assert_eq!(
             res,
-            "GET / HTTP/1.1\r\nWrapping: pre-update\r\nAnother: header\r\nContent-Length: 7\r\n\r\nTHE END"
+            "GET / HTTP/1.1\r\nWrapping: pre - update\r\nAnother: header\r\nContent-Length: 7\r\n\r\nTHE END"
         );
 
         r.headers
@@ -0,0 +1,91 @@
+use crate::errors::Errors;
+use crate::headers::is_tchar;
+use std::fmt;
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc7231#section-4.1
+
+    method = token
+
+    The method token is case-sensitive.  Anything other than the methods defined by
+    rfc7231 and rfc5789 (PATCH) is kept as an extension method.
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+    Extension(String),
+}
+
+impl Method {
+    pub fn parse(token: &str) -> Result<Self, Errors> {
+        if token.is_empty() || !token.bytes().all(is_tchar) {
+            return Err(Errors::RequestLine("invalid method"));
+        }
+        Ok(match token {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "CONNECT" => Method::Connect,
+            "OPTIONS" => Method::Options,
+            "TRACE" => Method::Trace,
+            "PATCH" => Method::Patch,
+            token => Method::Extension(token.to_owned()),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Connect => "CONNECT",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+            Method::Patch => "PATCH",
+            Method::Extension(token) => token,
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Method::parse("GET"), Ok(Method::Get));
+        assert_eq!(Method::parse("PATCH"), Ok(Method::Patch));
+        assert_eq!(
+            Method::parse("get"),
+            Ok(Method::Extension("get".to_owned()))
+        );
+        assert_eq!(Method::parse("PROPFIND").unwrap().to_string(), "PROPFIND");
+        assert_eq!(
+            Method::parse("GE(T"),
+            Err(Errors::RequestLine("invalid method"))
+        );
+        assert_eq!(
+            Method::parse(""),
+            Err(Errors::RequestLine("invalid method"))
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::errors::Errors;
+use crate::Request;
+
+// how much is asked of the reader at a time
+const SCRATCH_SIZE: usize = 8 * 1024;
+
+// what a single read_from call achieved
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadProgress {
+    // body_complete is true, anything read past the end of the message is in excess_bytes
+    Complete,
+    // call again once the reader has more to give
+    NeedsMoreData,
+    // the reader ended before any of the request arrived, e.g. an idle keep-alive connection
+    Closed,
+}
+
+impl Request {
+    // reads once from reader and parses what arrived
+    // NOTE: the reader ending part way through the request is an UnexpectedEof error
+    pub fn read_from_blocking<R: std::io::Read>(
+        &mut self,
+        reader: &mut R,
+    ) -> Result<ReadProgress, Errors> {
+        if self.body_complete() {
+            return Ok(ReadProgress::Complete);
+        }
+        let mut scratch = [0; SCRATCH_SIZE];
+        let read = reader
+            .read(&mut scratch)
+            .map_err(|e| Errors::Io(e.to_string()))?;
+        self.progress(&scratch[..read])
+    }
+
+    // the async version of read_from_blocking
+    #[cfg(feature = "tokio")]
+    pub async fn read_from<R: tokio::io::AsyncRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+    ) -> Result<ReadProgress, Errors> {
+        use tokio::io::AsyncReadExt;
+
+        if self.body_complete() {
+            return Ok(ReadProgress::Complete);
+        }
+        let mut scratch = [0; SCRATCH_SIZE];
+        let read = reader
+            .read(&mut scratch)
+            .await
+            .map_err(|e| Errors::Io(e.to_string()))?;
+        self.progress(&scratch[..read])
+    }
+
+    // an empty read means the reader has nothing more to give
+    fn progress(&mut self, read: &[u8]) -> Result<ReadProgress, Errors> {
+        if read.is_empty() {
+            // the empty lines skipped before a request line are not part of a request
+            if self.raw.is_empty() {
+                return Ok(ReadProgress::Closed);
+            }
+            return Err(Errors::UnexpectedEof);
+        }
+        self.update_raw(&mut read.to_vec())?;
+        if self.body_complete() {
+            Ok(ReadProgress::Complete)
+        } else {
+            Ok(ReadProgress::NeedsMoreData)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIRE: &[u8] = b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello";
+
+    // hands out one byte per read
+    struct Trickle<'a>(&'a [u8]);
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_read_from_blocking() {
+        let mut r = Request::default();
+        let mut reader = Trickle(WIRE);
+        let mut reads = 0;
+        while r.read_from_blocking(&mut reader) == Ok(ReadProgress::NeedsMoreData) {
+            reads += 1;
+        }
+        assert_eq!(reads, WIRE.len() - 1);
+        assert!(r.body_complete());
+        assert_eq!(r.body(), b"hello".to_vec());
+        // complete requests don't read any further
+        assert_eq!(
+            r.read_from_blocking(&mut Trickle(b"GET")),
+            Ok(ReadProgress::Complete)
+        );
+
+        let mut r = Request::default();
+        let mut reader = Trickle(&WIRE[..WIRE.len() - 2]);
+        let result = loop {
+            match r.read_from_blocking(&mut reader) {
+                Ok(ReadProgress::NeedsMoreData) => {}
+                result => break result,
+            }
+        };
+        assert_eq!(result, Err(Errors::UnexpectedEof));
+
+        let mut r = Request::default();
+        assert_eq!(
+            r.read_from_blocking(&mut Trickle(b"")),
+            Ok(ReadProgress::Closed)
+        );
+        let mut r = Request::default();
+        let mut reader = Trickle(b"\r\n");
+        while r.read_from_blocking(&mut reader) == Ok(ReadProgress::NeedsMoreData) {}
+        assert_eq!(r.read_from_blocking(&mut reader), Ok(ReadProgress::Closed));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_read_from() {
+        use tokio::io::AsyncWriteExt;
+
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let writer = tokio::spawn(async move {
+            for byte in WIRE {
+                client.write_all(&[*byte]).await.unwrap();
+            }
+        });
+        let mut r = Request::default();
+        while r.read_from(&mut server).await == Ok(ReadProgress::NeedsMoreData) {}
+        writer.await.unwrap();
+        assert!(r.body_complete());
+        assert_eq!(r.body(), b"hello".to_vec());
+
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client.write_all(&WIRE[..WIRE.len() - 2]).await.unwrap();
+        drop(client);
+        let mut r = Request::default();
+        let result = loop {
+            match r.read_from(&mut server).await {
+                Ok(ReadProgress::NeedsMoreData) => {}
+                result => break result,
+            }
+        };
+        assert_eq!(result, Err(Errors::UnexpectedEof));
+    }
+}
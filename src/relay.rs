@@ -0,0 +1,248 @@
+use crate::errors::Errors;
+use crate::headers::Headers;
+use crate::{dump_head, Chunked, ContentLength, HeadersEnd, Request};
+
+/*
+    https://www.rfc-editor.org/rfc/rfc7230#section-6.1
+
+    A proxy or gateway MUST parse a received Connection header field before a message is
+    forwarded and, for each connection-option in this field, remove any header field(s)
+    from the message with the same name as the connection-option, and then remove the
+    Connection header field itself (or replace it with the intermediary's own
+    connection options for the forwarded message).
+
+    https://www.rfc-editor.org/rfc/rfc7230#section-5.7.1
+
+    Via = 1#( received-protocol RWS received-by [ RWS comment ] )
+    received-protocol = [ protocol-name "/" ] protocol-version
+                      ; see Section 6.7
+    received-by       = ( uri-host [ ":" port ] ) / pseudonym
+*/
+
+// headers that only describe the connection the request arrived on
+// Transfer-Encoding and Content-Length are handled separately since the framing may change
+const HOP_BY_HOP: [&str; 6] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authorization",
+    "te",
+    "upgrade",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Framing {
+    // forward the body with the same framing it arrived with
+    Preserve,
+    // always forward a chunked body, e.g. when filtering means the length is no longer known
+    Chunked,
+}
+
+// forward a request upstream while it is still arriving:
+// head_bytes once the headers are complete, relay_chunk for each fragment read, then finish
+#[derive(Debug, Clone)]
+pub struct Relay {
+    pub request: Request,
+    pub framing: Framing,
+    // the received-by part of the Via header added upstream
+    pub via: Option<String>,
+    // the value of a Forwarded header added upstream, e.g. "for=192.0.2.60;proto=http"
+    pub forwarded: Option<String>,
+    relayed: usize,
+}
+
+impl Relay {
+    pub fn new(framing: Framing) -> Self {
+        Relay {
+            request: Request::default(),
+            framing,
+            via: None,
+            forwarded: None,
+            relayed: 0,
+        }
+    }
+
+    fn has_body(&self) -> bool {
+        self.request.is_chunked != Chunked::Unset
+            || self.request.content_length != ContentLength::Unset
+    }
+
+    fn chunked_output(&self) -> bool {
+        self.has_body()
+            && (self.framing == Framing::Chunked || self.request.is_chunked != Chunked::Unset)
+    }
+
+    // the head to send upstream, available as soon as the headers are complete
+    pub fn head_bytes(&self) -> Result<Vec<u8>, Errors> {
+        if !matches!(self.request.headers_end, HeadersEnd::FoundAt(_)) {
+            return Err(Errors::CannotFillHeaders);
+        }
+
+        let connection_options = self
+            .request
+            .headers
+            .get_all("connection")
+            .iter()
+            .flat_map(|h| h.value_list())
+            .collect::<Vec<String>>();
+        let chunked_output = self.chunked_output();
+
+        let mut headers = self
+            .request
+            .headers
+            .iter()
+            .filter(|header| {
+                let hop_by_hop = HOP_BY_HOP.iter().any(|name| header.name_is(name))
+                    || connection_options.iter().any(|name| header.name_is(name))
+                    || header.name_is("transfer-encoding")
+                    || (chunked_output && header.name_is("content-length"));
+                !hop_by_hop
+            })
+            .cloned()
+            .collect::<Headers>();
+        if chunked_output {
+            // any other codings still apply to the body, only the chunk framing is redone
+            let mut codings = self.request.headers.transfer_encodings();
+            codings.retain(|c| c != "chunked");
+            codings.push("chunked".to_owned());
+            headers.add("Transfer-Encoding".to_owned(), codings.join(", "))?;
+        }
+        if let Some(via) = &self.via {
+            let (major, minor) = self.request.http_version().unwrap_or((1, 1));
+            headers.add("Via".to_owned(), format!("{}.{} {}", major, minor, via))?;
+        }
+        if let Some(forwarded) = &self.forwarded {
+            headers.add("Forwarded".to_owned(), forwarded.clone())?;
+        }
+        Ok(dump_head(&self.request.request_line, &headers))
+    }
+
+    // parse the next fragment read from the client and return the framed body bytes
+    // that can be sent upstream because of it
+    pub fn relay_chunk(&mut self, fragment: &[u8]) -> Result<Vec<u8>, Errors> {
+        self.request.update_raw(&mut fragment.to_vec())?;
+
+        let body = self.request.body_slice();
+        let end = match (&self.request.is_chunked, &self.request.content_length) {
+            (Chunked::Unset, ContentLength::Value(content_length)) => {
+                body.len().min(*content_length)
+            }
+            (Chunked::Unset, ContentLength::Unset) => 0,
+            _ => body.len(),
+        };
+        let data = body[self.relayed..end].to_vec();
+        self.relayed = end;
+
+        if !self.chunked_output() || data.is_empty() {
+            return Ok(data);
+        }
+        let mut framed = format!("{:x}\r\n", data.len()).into_bytes();
+        framed.extend_from_slice(&data);
+        framed.extend_from_slice(b"\r\n");
+        Ok(framed)
+    }
+
+    // the end of a chunked body upstream: the last chunk and any trailers that were received
+    pub fn finish(&mut self) -> Vec<u8> {
+        if !self.chunked_output() {
+            return vec![];
+        }
+        let mut end = b"0\r\n".to_vec();
+        for trailer in self.request.trailers().values.iter() {
+            end.extend_from_slice(format!("{}: {}\r\n", trailer.key, trailer.value).as_bytes());
+        }
+        end.extend_from_slice(b"\r\n");
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // relay every fragment and collect what would have been written upstream
+    fn relay_all(relay: &mut Relay, fragments: &[&[u8]]) -> Vec<u8> {
+        let mut upstream = vec![];
+        let mut head_sent = false;
+        for fragment in fragments {
+            let body = relay.relay_chunk(fragment).unwrap();
+            if !head_sent {
+                if let Ok(mut head) = relay.head_bytes() {
+                    upstream.append(&mut head);
+                    head_sent = true;
+                }
+            }
+            upstream.extend_from_slice(&body);
+        }
+        assert!(relay.request.body_complete());
+        upstream.append(&mut relay.finish());
+        upstream
+    }
+
+    fn reparse(upstream: &[u8]) -> Request {
+        let mut r = Request::default();
+        assert_eq!(r.update_raw(&mut upstream.to_vec()), Ok(()));
+        assert!(r.body_complete());
+        r
+    }
+
+    const LENGTH: [&[u8]; 3] = [
+        b"POST /upload HTTP/1.1\r\nHost: x\r\nConnection: keep-alive, X-Hop\r\n",
+        b"X-Hop: 1\r\nContent-Length: 11\r\n\r\nhello",
+        b" world",
+    ];
+
+    #[test]
+    fn test_length_to_length() {
+        let mut relay = Relay::new(Framing::Preserve);
+        relay.via = Some("proxy".to_owned());
+        assert_eq!(relay.head_bytes(), Err(Errors::CannotFillHeaders));
+
+        let upstream = relay_all(&mut relay, &LENGTH);
+        assert_eq!(
+            upstream,
+            b"POST /upload HTTP/1.1\r\nHost: x\r\nContent-Length: 11\r\nVia: 1.1 proxy\r\n\r\nhello world"
+                .to_vec()
+        );
+        assert_eq!(reparse(&upstream).body(), b"hello world".to_vec());
+    }
+
+    #[test]
+    fn test_length_to_chunked() {
+        let mut relay = Relay::new(Framing::Chunked);
+        relay.forwarded = Some("for=192.0.2.60".to_owned());
+
+        let upstream = relay_all(&mut relay, &LENGTH);
+        assert_eq!(
+            upstream,
+            b"POST /upload HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\nForwarded: for=192.0.2.60\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n"
+                .to_vec()
+        );
+        let r = reparse(&upstream);
+        assert_eq!(r.body(), b"hello world".to_vec());
+        assert!(r.headers.get("content-length").is_none());
+    }
+
+    #[test]
+    fn test_chunked_to_chunked_with_trailers() {
+        let mut relay = Relay::new(Framing::Preserve);
+        let upstream = relay_all(
+            &mut relay,
+            &[
+                b"POST / HTTP/1.1\r\nTE: trailers\r\nTransfer-Encoding: gzip, chunked\r\n\r\n4\r\nWi",
+                b"ki\r\n7\r\npedia i\r\n0\r\nExpires: never\r\n",
+                b"\r\n",
+            ],
+        );
+        let r = reparse(&upstream);
+        assert_eq!(r.body(), b"Wikipedia i".to_vec());
+        assert_eq!(r.trailers().len(), 1);
+        assert_eq!(r.trailers().values[0].to_string(), "Expires: never");
+        assert!(r.headers.get("te").is_none());
+        assert_eq!(r.headers.len(), 1);
+        assert_eq!(
+            r.headers.values[0].to_string(),
+            "Transfer-Encoding: gzip, chunked"
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use crate::errors::Errors;
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc3986#section-2.1
+
+    pct-encoded = "%" HEXDIG HEXDIG
+*/
+
+pub fn percent_decode(input: &str, plus_as_space: bool) -> Result<Vec<u8>, Errors> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                if i + 2 >= bytes.len() {
+                    return Err(Errors::PercentDecode);
+                }
+                let hi = hex_value(bytes[i + 1]).ok_or(Errors::PercentDecode)?;
+                let lo = hex_value(bytes[i + 2]).ok_or(Errors::PercentDecode)?;
+                decoded.push(hi << 4 | lo);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Ok(decoded)
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/*
+    https://www.rfc-editor.org/rfc/rfc7230#section-5.3
+
+    request-target = origin-form
+                   / absolute-form
+                   / authority-form
+                   / asterisk-form
+
+    origin-form    = absolute-path [ "?" query ]
+    absolute-form  = absolute-URI
+    authority-form = authority
+    asterisk-form  = "*"
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target<'a> {
+    // only present for absolute-form ("http://host/path") and authority-form ("host:443")
+    pub authority: Option<&'a str>,
+    pub path: &'a str,
+    pub query: Option<&'a str>,
+}
+
+// split a request-target into its (still percent-encoded) components
+pub fn split(target: &str) -> Target<'_> {
+    let (rest, query) = match target.split_once('?') {
+        Some((rest, query)) => (rest, Some(query)),
+        None => (target, None),
+    };
+    if rest.starts_with('/') || rest == "*" {
+        return Target {
+            authority: None,
+            path: rest,
+            query,
+        };
+    }
+
+    // scheme = ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )
+    let absolute = rest.split_once("://").filter(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    match absolute {
+        Some((_, hier)) => {
+            let (authority, path) = match hier.find('/') {
+                Some(at) => (&hier[..at], &hier[at..]),
+                // an empty path is the same as "/": https://www.rfc-editor.org/rfc/rfc7230#section-2.7.3
+                None => (hier, "/"),
+            };
+            Target {
+                authority: Some(authority),
+                path,
+                query,
+            }
+        }
+        None => Target {
+            authority: Some(rest),
+            path: "",
+            query,
+        },
+    }
+}
+
+/*
+    https://www.rfc-editor.org/rfc/rfc7230#section-5.4
+
+    Host = uri-host [ ":" port ]
+
+    https://datatracker.ietf.org/doc/html/rfc3986#section-3.2.2
+
+    IP-literal = "[" ( IPv6address / IPvFuture  ) "]"
+*/
+
+// the name and port of a Host value or authority, an IPv6 literal is returned without brackets
+pub fn split_host(authority: &str) -> Option<(&str, Option<u16>)> {
+    // userinfo is not allowed in a Host header but can appear in an absolute-form target
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, a)| a);
+    let (name, port) = match authority.strip_prefix('[') {
+        Some(literal) => {
+            let (name, rest) = literal.split_once(']')?;
+            match rest {
+                "" => (name, None),
+                _ => (name, Some(rest.strip_prefix(':')?)),
+            }
+        }
+        None => match authority.split_once(':') {
+            Some((name, port)) => (name, Some(port)),
+            None => (authority, None),
+        },
+    };
+    if name.is_empty() {
+        return None;
+    }
+    let port = match port {
+        // "example.com:" has an empty port, which is the same as no port
+        Some("") | None => None,
+        Some(port) if port.bytes().all(|b| b.is_ascii_digit()) => Some(port.parse().ok()?),
+        Some(_) => return None,
+    };
+    Some((name, port))
+}
+
+// application/x-www-form-urlencoded pairs, in order and including repeated keys
+// a pair without "=" has an empty value
+pub fn parse_query(query: &str) -> Result<Vec<(String, String)>, Errors> {
+    let mut pairs = vec![];
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        pairs.push((
+            String::from_utf8(percent_decode(key, true)?)?,
+            String::from_utf8(percent_decode(value, true)?)?,
+        ));
+    }
+    Ok(pairs)
+}
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc3986#section-5.2.4
+
+    Remove the special "." and ".." complete path segments.  A ".." never
+    climbs above the root, so "/../etc/passwd" becomes "/etc/passwd".
+*/
+
+pub fn remove_dot_segments(path: &str, collapse_slashes: bool) -> String {
+    // only the root slash goes, any empty segments after it are kept unless collapsed
+    let segments = path
+        .strip_prefix('/')
+        .unwrap_or(path)
+        .split('/')
+        .collect::<Vec<&str>>();
+
+    let mut output: Vec<&str> = vec![];
+    for (i, segment) in segments.iter().enumerate() {
+        let is_last = i == segments.len() - 1;
+        match *segment {
+            "." => {
+                if is_last {
+                    output.push("");
+                }
+            }
+            ".." => {
+                output.pop();
+                if is_last {
+                    output.push("");
+                }
+            }
+            "" if collapse_slashes && !is_last => {}
+            segment => output.push(segment),
+        }
+    }
+    format!("/{}", output.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_host() {
+        assert_eq!(split_host("example.com"), Some(("example.com", None)));
+        assert_eq!(
+            split_host("example.com:8080"),
+            Some(("example.com", Some(8080)))
+        );
+        assert_eq!(split_host("example.com:"), Some(("example.com", None)));
+        assert_eq!(split_host("[::1]:443"), Some(("::1", Some(443))));
+        assert_eq!(split_host("[::1]"), Some(("::1", None)));
+        assert_eq!(split_host("user@host:1"), Some(("host", Some(1))));
+        assert_eq!(split_host(""), None);
+        assert_eq!(split_host(":80"), None);
+        assert_eq!(split_host("[::1"), None);
+        assert_eq!(split_host("[::1]443"), None);
+        assert_eq!(split_host("host:http"), None);
+        assert_eq!(split_host("host:65536"), None);
+        assert_eq!(split_host("::1"), None);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/a%20b", false), Ok(b"/a b".to_vec()));
+        assert_eq!(percent_decode("/a+b", false), Ok(b"/a+b".to_vec()));
+        assert_eq!(percent_decode("a+b", true), Ok(b"a b".to_vec()));
+        assert_eq!(percent_decode("%2f%2F", false), Ok(b"//".to_vec()));
+        assert_eq!(percent_decode("/bad%2", false), Err(Errors::PercentDecode));
+        assert_eq!(percent_decode("/bad%", false), Err(Errors::PercentDecode));
+        assert_eq!(percent_decode("/bad%G1", false), Err(Errors::PercentDecode));
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("/a/b?x=1"),
+            Target {
+                authority: None,
+                path: "/a/b",
+                query: Some("x=1")
+            }
+        );
+        assert_eq!(
+            split("http://host:8080/path?x=1"),
+            Target {
+                authority: Some("host:8080"),
+                path: "/path",
+                query: Some("x=1")
+            }
+        );
+        assert_eq!(
+            split("https://host"),
+            Target {
+                authority: Some("host"),
+                path: "/",
+                query: None
+            }
+        );
+        assert_eq!(
+            split("example.com:443"),
+            Target {
+                authority: Some("example.com:443"),
+                path: "",
+                query: None
+            }
+        );
+        assert_eq!(split("*").path, "*");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("a=1&b=x+y%2B&a=2&flag&&=v"),
+            Ok(vec![
+                ("a".to_owned(), "1".to_owned()),
+                ("b".to_owned(), "x y+".to_owned()),
+                ("a".to_owned(), "2".to_owned()),
+                ("flag".to_owned(), "".to_owned()),
+                ("".to_owned(), "v".to_owned()),
+            ])
+        );
+        assert_eq!(parse_query(""), Ok(vec![]));
+        assert_eq!(parse_query("a=%zz"), Err(Errors::PercentDecode));
+    }
+
+    #[test]
+    fn test_remove_dot_segments() {
+        assert_eq!(remove_dot_segments("/a/../b", false), "/b");
+        assert_eq!(remove_dot_segments("/a/./b", false), "/a/b");
+        assert_eq!(remove_dot_segments("/../etc/passwd", false), "/etc/passwd");
+        assert_eq!(remove_dot_segments("/a/b/..", false), "/a/");
+        assert_eq!(remove_dot_segments("/a/b/.", false), "/a/b/");
+        assert_eq!(remove_dot_segments("/", false), "/");
+        assert_eq!(remove_dot_segments("/a//b/", false), "/a//b/");
+        assert_eq!(remove_dot_segments("/a//b//", true), "/a/b/");
+        assert_eq!(remove_dot_segments("//../../a", true), "/a");
+        assert_eq!(remove_dot_segments("//a//b", false), "//a//b");
+        assert_eq!(remove_dot_segments("//a//b", true), "/a/b");
+    }
+}
@@ -0,0 +1,341 @@
+use crate::Request;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/*
+    Fixture driven parsing checks.
+
+    A fixture is a pair of files sharing a name:
+
+        name.http   raw bytes exactly as they would arrive on the wire
+        name.toml   the expected outcome of feeding those bytes to a Request
+
+    The descriptor is a flat subset of TOML, every key is optional:
+
+        status = "complete"             # complete | incomplete | error
+        error = "HeaderKeyWhitespace"   # Errors variant name when status = "error"
+        request_line = "GET / HTTP/1.1"
+        header_count = 2
+        body_length = 4
+        fragments = [3, 17]             # offsets at which raw is split across update_raw calls
+
+    Keys that are left out of the descriptor are not compared.
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Complete,
+    Incomplete,
+    Error,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Complete => write!(f, "complete"),
+            Status::Incomplete => write!(f, "incomplete"),
+            Status::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Descriptor {
+    pub status: Option<Status>,
+    pub error: Option<String>,
+    pub request_line: Option<String>,
+    pub header_count: Option<usize>,
+    pub body_length: Option<usize>,
+    pub fragments: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(usize),
+    Boolean(bool),
+    Array(Vec<usize>),
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Some(s) = value.strip_prefix('"') {
+        return match s.strip_suffix('"') {
+            Some(s) => Ok(Value::String(s.replace("\\\"", "\"").replace("\\\\", "\\"))),
+            None => Err(format!("unterminated string: {}", value)),
+        };
+    }
+    if let Some(a) = value.strip_prefix('[') {
+        let a = match a.strip_suffix(']') {
+            Some(a) => a,
+            None => return Err(format!("unterminated array: {}", value)),
+        };
+        let mut values = vec![];
+        for v in a.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()) {
+            match v.parse::<usize>() {
+                Ok(v) => values.push(v),
+                Err(_) => return Err(format!("arrays may only contain integers: {}", value)),
+            }
+        }
+        return Ok(Value::Array(values));
+    }
+    match value {
+        "true" => return Ok(Value::Boolean(true)),
+        "false" => return Ok(Value::Boolean(false)),
+        _ => {}
+    }
+    match value.parse::<usize>() {
+        Ok(v) => Ok(Value::Integer(v)),
+        Err(_) => Err(format!("unsupported value: {}", value)),
+    }
+}
+
+impl Descriptor {
+    pub fn parse(toml: &str) -> Result<Self, String> {
+        let mut descriptor = Descriptor::default();
+
+        for (number, line) in toml.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("line {}: expected key = value", number + 1)),
+            };
+            // strip a trailing comment unless it is inside of a string
+            let value = match value.rfind(" #") {
+                Some(at) if !value.starts_with('"') || value[..at].ends_with('"') => {
+                    value[..at].trim()
+                }
+                _ => value,
+            };
+            let value = parse_value(value).map_err(|e| format!("line {}: {}", number + 1, e))?;
+
+            match (key, value) {
+                ("status", Value::String(s)) => {
+                    descriptor.status = Some(match s.as_str() {
+                        "complete" => Status::Complete,
+                        "incomplete" => Status::Incomplete,
+                        "error" => Status::Error,
+                        _ => return Err(format!("line {}: unknown status {}", number + 1, s)),
+                    })
+                }
+                ("error", Value::String(s)) => descriptor.error = Some(s),
+                ("request_line", Value::String(s)) => descriptor.request_line = Some(s),
+                ("header_count", Value::Integer(i)) => descriptor.header_count = Some(i),
+                ("body_length", Value::Integer(i)) => descriptor.body_length = Some(i),
+                ("fragments", Value::Array(a)) => descriptor.fragments = a,
+                (key, value) => {
+                    return Err(format!(
+                        "line {}: unexpected key or type: {} = {:?}",
+                        number + 1,
+                        key,
+                        value
+                    ))
+                }
+            }
+        }
+        Ok(descriptor)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixture {
+    pub name: String,
+    pub raw: Vec<u8>,
+    pub descriptor: Descriptor,
+}
+
+impl Fixture {
+    // load name.http and the name.toml descriptor next to it
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let raw = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let descriptor_path = path.with_extension("toml");
+        let toml = fs::read_to_string(&descriptor_path)
+            .map_err(|e| format!("{}: {}", descriptor_path.display(), e))?;
+        let descriptor = Descriptor::parse(&toml)
+            .map_err(|e| format!("{}: {}", descriptor_path.display(), e))?;
+
+        let name = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => path.display().to_string(),
+        };
+        Ok(Fixture {
+            name,
+            raw,
+            descriptor,
+        })
+    }
+
+    // every *.http file in a directory, sorted by name
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<Self>, String> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+        let mut paths = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().map(|e| e == "http").unwrap_or(false))
+            .collect::<Vec<PathBuf>>();
+        paths.sort();
+        paths.iter().map(Fixture::load).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub status: Status,
+    pub error: Option<String>,
+    pub request_line: String,
+    pub header_count: usize,
+    pub body_length: usize,
+}
+
+// feed the fixture to a fresh Request, split at the descriptor's fragment offsets
+pub fn run(fixture: &Fixture) -> Outcome {
+    let mut request = Request::default();
+    let mut error = None;
+
+    let mut start = 0;
+    let mut offsets = fixture.descriptor.fragments.clone();
+    offsets.push(fixture.raw.len());
+    for end in offsets {
+        let end = end.clamp(start, fixture.raw.len());
+        if let Err(e) = request.update_raw(&mut fixture.raw[start..end].to_vec()) {
+            // the variant name without any of its contents
+            let name = format!("{:?}", e);
+            let name = match name.split_once('(') {
+                Some((name, _)) => name.to_owned(),
+                None => name,
+            };
+            error = Some(name);
+            break;
+        }
+        start = end;
+    }
+
+    let status = if error.is_some() {
+        Status::Error
+    } else if request.body_complete() {
+        Status::Complete
+    } else {
+        Status::Incomplete
+    };
+    Outcome {
+        status,
+        error,
+        request_line: request.request_line.clone(),
+        header_count: request.headers.len(),
+        body_length: request.body().len(),
+    }
+}
+
+fn diff_line<T: fmt::Display + PartialEq>(
+    lines: &mut Vec<String>,
+    field: &str,
+    expected: &Option<T>,
+    actual: &T,
+) -> bool {
+    match expected {
+        None => true,
+        Some(expected) if expected == actual => {
+            lines.push(format!("  {}: {}", field, actual));
+            true
+        }
+        Some(expected) => {
+            lines.push(format!("- {}: {}", field, expected));
+            lines.push(format!("+ {}: {}", field, actual));
+            false
+        }
+    }
+}
+
+// compare a fixture against its descriptor, the error is a diff of expected (-) vs actual (+)
+pub fn check(fixture: &Fixture) -> Result<(), String> {
+    let outcome = run(fixture);
+    let expected = &fixture.descriptor;
+
+    let mut lines = vec![];
+    let mut matches = true;
+    matches &= diff_line(&mut lines, "status", &expected.status, &outcome.status);
+    matches &= diff_line(
+        &mut lines,
+        "error",
+        &expected.error,
+        &outcome.error.clone().unwrap_or_default(),
+    );
+    matches &= diff_line(
+        &mut lines,
+        "request_line",
+        &expected.request_line,
+        &outcome.request_line,
+    );
+    matches &= diff_line(
+        &mut lines,
+        "header_count",
+        &expected.header_count,
+        &outcome.header_count,
+    );
+    matches &= diff_line(
+        &mut lines,
+        "body_length",
+        &expected.body_length,
+        &outcome.body_length,
+    );
+
+    if matches {
+        Ok(())
+    } else {
+        Err(format!("fixture {}:\n{}", fixture.name, lines.join("\n")))
+    }
+}
+
+pub fn assert_parses(fixture: &Fixture) {
+    if let Err(diff) = check(fixture) {
+        panic!("{}", diff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descriptor() {
+        let d = Descriptor::parse(
+            "# comment\nstatus = \"error\"\nerror = \"Header\" # trailing\nheader_count = 2\nfragments = [1, 5]\n",
+        )
+        .unwrap();
+        assert_eq!(d.status, Some(Status::Error));
+        assert_eq!(d.error, Some("Header".to_owned()));
+        assert_eq!(d.header_count, Some(2));
+        assert_eq!(d.body_length, None);
+        assert_eq!(d.fragments, vec![1, 5]);
+
+        assert!(Descriptor::parse("status = \"unknown\"").is_err());
+        assert!(Descriptor::parse("header_count = \"two\"").is_err());
+        assert!(Descriptor::parse("nonsense").is_err());
+    }
+
+    #[test]
+    fn test_check_diff() {
+        let fixture = Fixture {
+            name: "inline".to_owned(),
+            raw: b"GET / HTTP/1.1\r\nA: b\r\n\r\n".to_vec(),
+            descriptor: Descriptor {
+                status: Some(Status::Complete),
+                header_count: Some(2),
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            check(&fixture),
+            Err(
+                "fixture inline:\n  status: complete\n- header_count: 2\n+ header_count: 1"
+                    .to_owned()
+            )
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use crate::errors::Errors;
+
+/*
+    https://datatracker.ietf.org/doc/html/rfc7231#section-5.3.1
+
+    weight = OWS ";" OWS "q=" qvalue
+    qvalue = ( "0" [ "." 0*3DIGIT ] )
+           / ( "1" [ "." 0*3("0") ] )
+*/
+
+// a "q=0.9" weight parameter, the parameter name is case-insensitive
+pub fn parse_quality_value(s: &str) -> Result<f32, Errors> {
+    match s.split_once('=') {
+        Some((name, value)) if name.eq_ignore_ascii_case("q") => qvalue(value),
+        _ => Err(Errors::QualityValue(s.to_owned())),
+    }
+}
+
+// just the qvalue part of a weight
+pub(crate) fn qvalue(value: &str) -> Result<f32, Errors> {
+    let invalid = || Errors::QualityValue(value.to_owned());
+    let (whole, fraction) = match value.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (value, ""),
+    };
+    if fraction.len() > 3 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    match whole {
+        "0" => {}
+        "1" if fraction.bytes().all(|b| b == b'0') => {}
+        _ => return Err(invalid()),
+    }
+    value.parse::<f32>().map_err(|_| invalid())
+}
+
+// the index of the first LF at or after start
+pub fn scan_for_lf(data: &[u8], start: usize) -> Option<usize> {
+    data.get(start..)?
+        .iter()
+        .position(|b| *b == b'\n')
+        .map(|i| start + i)
+}
+
+// the index of the CR of the first CRLF at or after start
+pub fn scan_for_crlf(data: &[u8], start: usize) -> Option<usize> {
+    let mut at = start;
+    loop {
+        let lf = scan_for_lf(data, at + 1)?;
+        if data[lf - 1] == b'\r' {
+            return Some(lf - 1);
+        }
+        at = lf;
+    }
+}
+
+// bytes as a base64 string rather than an array of numbers: #[serde(with = "util::base64_bytes")]
+#[cfg(feature = "serde")]
+pub(crate) mod base64_bytes {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let data = b"a\nb\r\nc\r\n";
+        assert_eq!(scan_for_lf(data, 0), Some(1));
+        assert_eq!(scan_for_lf(data, 2), Some(4));
+        assert_eq!(scan_for_lf(data, 8), None);
+        assert_eq!(scan_for_lf(data, 100), None);
+
+        assert_eq!(scan_for_crlf(data, 0), Some(3));
+        assert_eq!(scan_for_crlf(data, 3), Some(3));
+        assert_eq!(scan_for_crlf(data, 4), Some(6));
+        assert_eq!(scan_for_crlf(data, 7), None);
+        assert_eq!(scan_for_crlf(b"\r", 0), None);
+        assert_eq!(scan_for_crlf(b"", 0), None);
+        assert_eq!(scan_for_crlf(b"\r\n", 0), Some(0));
+    }
+
+    #[test]
+    fn test_parse_quality_value() {
+        assert_eq!(parse_quality_value("q=0.9"), Ok(0.9));
+        assert_eq!(parse_quality_value("Q=1"), Ok(1.0));
+        assert_eq!(parse_quality_value("q=1.000"), Ok(1.0));
+        assert_eq!(parse_quality_value("q=0"), Ok(0.0));
+        assert_eq!(parse_quality_value("q=0."), Ok(0.0));
+        assert_eq!(parse_quality_value("q=0.125"), Ok(0.125));
+        for invalid in [
+            "q=0.1234", "q=1.001", "q=2", "q=-0", "q=.5", "q=", "q=0.5x", "x=0.5", "0.5",
+        ] {
+            assert!(parse_quality_value(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!(
+            parse_quality_value("q=1.5"),
+            Err(Errors::QualityValue("1.5".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use httplus::intern::Session;
+use httplus::Request;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// counts every allocation made by this test binary, which is why it holds a single test
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const REQUESTS: usize = 100;
+const RAW: &[u8] = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\nUser-Agent: test\r\nAccept: text/html\r\nAccept-Language: en\r\nAccept-Encoding: gzip\r\nConnection: keep-alive\r\nCookie: a=b\r\nCache-Control: no-cache\r\n\r\n";
+const HEADERS: usize = 8;
+
+// replay the same request REQUESTS times, as a keep-alive connection would see it
+fn allocations_per_request(new_request: impl Fn() -> Request) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..REQUESTS {
+        let mut request = new_request();
+        request.update_raw(&mut RAW.to_vec()).unwrap();
+        assert_eq!(request.headers.len(), HEADERS);
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) / REQUESTS
+}
+
+#[test]
+fn test_interning_saves_an_allocation_per_header() {
+    let plain = allocations_per_request(Request::default);
+    let session = Session::default();
+    let interned = allocations_per_request(|| session.request());
+    assert!(
+        interned + HEADERS <= plain,
+        "allocations per request: {} plain, {} interned",
+        plain,
+        interned
+    );
+}
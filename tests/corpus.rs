@@ -0,0 +1,14 @@
+use httplus::testing::{check, Fixture};
+
+#[test]
+fn test_fixtures() {
+    let fixtures =
+        Fixture::load_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).unwrap();
+    assert!(!fixtures.is_empty());
+
+    let failures = fixtures
+        .iter()
+        .filter_map(|f| check(f).err())
+        .collect::<Vec<String>>();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+}
@@ -0,0 +1,47 @@
+use httplus::headers::Header;
+use httplus::Request;
+use std::fs;
+use std::path::Path;
+
+// the seed corpus in fuzz/seeds holds inputs that once crashed a fuzz target, replaying them
+// here the same way the targets do keeps them from coming back without a nightly toolchain
+fn seeds(target: &str) -> Vec<Vec<u8>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/seeds")
+        .join(target);
+    let seeds = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| fs::read(entry.unwrap().path()).unwrap())
+        .collect::<Vec<Vec<u8>>>();
+    assert!(!seeds.is_empty());
+    seeds
+}
+
+// see fuzz/fuzz_targets/parse_request.rs
+#[test]
+fn test_parse_request_seeds() {
+    for seed in seeds("parse_request") {
+        let Some((step, data)) = seed.split_first() else {
+            continue;
+        };
+        let step = (*step as usize).max(1);
+
+        let mut r = Request::default();
+        if data
+            .chunks(step)
+            .all(|piece| r.update_raw(&mut piece.to_vec()).is_ok())
+            && r.body_complete()
+        {
+            let mut replayed = Request::default();
+            let _ = replayed.update_raw(&mut r.dump());
+        }
+    }
+}
+
+// see fuzz/fuzz_targets/header_new.rs
+#[test]
+fn test_header_new_seeds() {
+    for seed in seeds("header_new") {
+        let _ = Header::new(seed);
+    }
+}